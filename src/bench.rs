@@ -0,0 +1,79 @@
+use std::time::Instant;
+
+use crate::board_state::BoardState;
+use crate::search::{SearchLimits, Searcher};
+
+pub const DEFAULT_BENCH_DEPTH: i32 = 4;
+pub const DEFAULT_THREAD_BENCH_DEPTH: i32 = 7;
+
+const BENCH_POSITIONS: [&str; 6] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+/// searches every bench position to the same depth with and without move ordering and prints the nodes needed
+pub fn run_node_benchmark(depth: i32) {
+    println!("{:<10}{:>14}{:>14}{:>10}", "position", "unordered", "ordered", "saved");
+    let mut total_unordered = 0;
+    let mut total_ordered = 0;
+    let start = Instant::now();
+    for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let bs = BoardState::from_fen(fen);
+        let mut nodes = [0; 2];
+        for (j, move_ordering) in [false, true].iter().enumerate() {
+            let mut searcher = Searcher::new();
+            searcher.options.move_ordering = *move_ordering;
+            nodes[j] = searcher.search(&bs, &SearchLimits::depth(depth)).nodes;
+        }
+        total_unordered += nodes[0];
+        total_ordered += nodes[1];
+        println!(
+            "{:<10}{:>14}{:>14}{:>9}%",
+            i + 1,
+            nodes[0],
+            nodes[1],
+            saved_percentage(nodes[0], nodes[1])
+        );
+    }
+    println!(
+        "{:<10}{:>14}{:>14}{:>9}%",
+        "total",
+        total_unordered,
+        total_ordered,
+        saved_percentage(total_unordered, total_ordered)
+    );
+    println!("depth {} in {:.1}s", depth, start.elapsed().as_secs_f64());
+}
+
+/// time to depth over all bench positions with a growing number of threads, lazy SMP should reach the depth faster
+pub fn run_thread_benchmark(depth: i32, max_threads: usize) {
+    println!("{:<10}{:>12}{:>14}{:>10}", "threads", "seconds", "nodes", "speedup");
+    let mut single_thread_seconds = 0.0;
+    let mut threads = 1;
+    while threads <= max_threads {
+        let mut nodes = 0;
+        let start = Instant::now();
+        for fen in BENCH_POSITIONS {
+            let mut searcher = Searcher::new();
+            searcher.set_threads(threads);
+            nodes += searcher.search(&BoardState::from_fen(fen), &SearchLimits::depth(depth)).nodes;
+        }
+        let seconds = start.elapsed().as_secs_f64();
+        if threads == 1 {
+            single_thread_seconds = seconds;
+        }
+        println!("{:<10}{:>12.2}{:>14}{:>9.2}x", threads, seconds, nodes, single_thread_seconds / seconds);
+        threads *= 2;
+    }
+}
+
+fn saved_percentage(before: u64, after: u64) -> i64 {
+    if before == 0 {
+        return 0;
+    }
+    return 100 - (after * 100 / before) as i64;
+}
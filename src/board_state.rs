@@ -1,10 +1,13 @@
-const NOTHING: i32 = 0;
-const PAWN: i32 = 1;
-const ROOK: i32 = 2;
-const KNIGHT: i32 = 3;
-const BISHOP: i32 = 4;
-const QUEEN: i32 = 5;
-const KING: i32 = 6;
+use crate::polyglot_keys;
+use crate::zobrist;
+
+pub const NOTHING: i32 = 0;
+pub const PAWN: i32 = 1;
+pub const ROOK: i32 = 2;
+pub const KNIGHT: i32 = 3;
+pub const BISHOP: i32 = 4;
+pub const QUEEN: i32 = 5;
+pub const KING: i32 = 6;
 
 pub const WHITE: i32 = 8;
 pub const BLACK: i32 = 16;
@@ -13,8 +16,11 @@ pub(crate) const INVALID_POSITION: Position = Position {
     row: 1234567890,
     col: 1234567890,
 };
-const PIECE_MASK: i32 = 7;
-const COLOR_MASK: i32 = 24;
+pub const PIECE_MASK: i32 = 7;
+pub const COLOR_MASK: i32 = 24;
+
+/// values in centipawns indexed by the colorless piece
+pub const PIECE_VALUES: [i32; 7] = [0, 100, 500, 300, 300, 900, 20000];
 
 pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 pub const E4_FEN: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
@@ -68,6 +74,39 @@ impl PartialEq<Self> for Position {
     }
 }
 
+/// a move from one square to another, promotion is the colorless piece a pawn turns into or NOTHING
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move {
+    pub(crate) from: Position,
+    pub(crate) to: Position,
+    pub(crate) promotion: i32,
+}
+
+impl Move {
+    pub fn new(from: Position, to: Position) -> Move {
+        return Move {
+            from,
+            to,
+            promotion: NOTHING,
+        };
+    }
+
+    pub fn to(&self) -> Position {
+        return self.to;
+    }
+
+    /// coordinate notation as used by UCI, e.g. "e2e4" or "e7e8q"
+    pub fn str(&self) -> String {
+        let mut result = self.from.str();
+        result.push_str(&self.to.str());
+        if self.promotion != NOTHING {
+            result.push(char_from_piece(self.promotion | BLACK));
+        }
+        return result;
+    }
+}
+
+#[derive(Clone)]
 pub struct BoardState {
     // [0]=h1 [1]=g1... [63]=a8
     board: [i32; 64],
@@ -76,14 +115,22 @@ pub struct BoardState {
     en_passant_square: Position,
     half_move_clock: u32,
     full_move_clock: u32,
+    // kept up to date by every move instead of hashing the whole board each time
+    zobrist_key: u64,
+}
+
+impl Default for BoardState {
+    fn default() -> Self {
+        return BoardState::new();
+    }
 }
 
 impl BoardState {
     pub fn from_fen(fen: &str) -> BoardState {
         let split_string: Vec<&str> = fen.split(' ').collect();
         let mut string_array: [&str; 6] = [""; 6];
-        for i in 0..split_string.len() {
-            string_array[i] = split_string.get(i).expect("FEN invalid");
+        for (i, part) in split_string.iter().enumerate() {
+            string_array[i] = part;
         }
         let board = fen_to_board(&string_array[0]);
         let color_to_move = if string_array[1] == "w" { WHITE } else { BLACK };
@@ -91,14 +138,71 @@ impl BoardState {
         let en_passant_square = Position::position_from_string(string_array[3]);
         let half_move_clock: u32 = string_array[4].parse().expect("Not a valid clock");
         let full_move_clock: u32 = string_array[5].parse().expect("Not a valid clock");
-        return BoardState {
+        let mut bs = BoardState {
             board,
             color_to_move,
             castling_rights,
             en_passant_square,
             half_move_clock,
             full_move_clock,
+            zobrist_key: 0,
         };
+        bs.zobrist_key = bs.compute_zobrist_key();
+        return bs;
+    }
+
+    /// like from_fen, for FENs from outside that can be malformed: all six fields are checked, each side needs one king,
+    /// pawns can't stand on the first or last rank and the side that just moved can't be in check
+    pub fn try_from_fen(fen: &str) -> Result<BoardState, String> {
+        let invalid = |reason: &str| format!("Invalid FEN {}: {}", fen, reason);
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let [placement, color, castling, en_passant, half_moves, full_moves] = fields[..] else {
+            return Err(invalid("it needs six fields"));
+        };
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(invalid("the board needs eight ranks"));
+        }
+        for (index, rank) in ranks.iter().enumerate() {
+            let mut squares = 0;
+            for ch in rank.chars() {
+                squares += match ch {
+                    '1'..='8' => ch.to_digit(10).expect("a digit"),
+                    'p' | 'P' if index == 0 || index == 7 => return Err(invalid("pawns can't stand on the first or last rank")),
+                    'r' | 'n' | 'b' | 'q' | 'k' | 'p' | 'R' | 'N' | 'B' | 'Q' | 'K' | 'P' => 1,
+                    _ => return Err(invalid("unknown piece")),
+                };
+            }
+            if squares != 8 {
+                return Err(invalid("every rank needs eight squares"));
+            }
+        }
+        if placement.matches('K').count() != 1 || placement.matches('k').count() != 1 {
+            return Err(invalid("each side needs one king"));
+        }
+        if color != "w" && color != "b" {
+            return Err(invalid("the side to move is w or b"));
+        }
+        if castling != "-" && (castling.is_empty() || !castling.chars().all(|ch| "KQkq".contains(ch))) {
+            return Err(invalid("castling rights are - or some of KQkq"));
+        }
+        let is_en_passant_square = |square: &str| {
+            let mut chars = square.chars();
+            return square.len() == 2
+                && chars.next().is_some_and(|file| ('a'..='h').contains(&file))
+                && chars.next().is_some_and(|rank| rank == '3' || rank == '6');
+        };
+        if en_passant != "-" && !is_en_passant_square(en_passant) {
+            return Err(invalid("the en passant square is - or a square on the third or sixth rank"));
+        }
+        if half_moves.parse::<u32>().is_err() || full_moves.parse::<u32>().is_err() {
+            return Err(invalid("the clocks have to be numbers"));
+        }
+        let bs = BoardState::from_fen(&fields.join(" "));
+        if bs.is_color_in_check(opposite_color(bs.color_to_move)) {
+            return Err(invalid("the side that just moved is in check"));
+        }
+        return Ok(bs);
     }
 
     pub fn new() -> BoardState {
@@ -158,7 +262,42 @@ impl BoardState {
     }
 
     pub fn to_fen(&self) -> String {
-        todo!()
+        let mut rows: Vec<String> = Vec::new();
+        for row in (0..8).rev() {
+            let mut row_string = String::new();
+            let mut empty_squares = 0;
+            for col in 0..8 {
+                let piece = self.get_piece_at_position(Position { row, col });
+                if piece == NOTHING {
+                    empty_squares += 1;
+                    continue;
+                }
+                if empty_squares > 0 {
+                    row_string.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                row_string.push(char_from_piece(piece));
+            }
+            if empty_squares > 0 {
+                row_string.push_str(&empty_squares.to_string());
+            }
+            rows.push(row_string);
+        }
+        let color = if self.color_to_move == WHITE { "w" } else { "b" };
+        let en_passant = if self.en_passant_square == INVALID_POSITION {
+            String::from("-")
+        } else {
+            self.en_passant_square.str()
+        };
+        return format!(
+            "{} {} {} {} {} {}",
+            rows.join("/"),
+            color,
+            self.castling_rights,
+            en_passant,
+            self.half_move_clock,
+            self.full_move_clock
+        );
     }
 
     pub fn get_piece_moves_respecting_checks(&self, position: Position) -> Vec<Position> {
@@ -209,8 +348,10 @@ impl BoardState {
      */
     pub fn perform_move(&self, position: Position, new_position: Position) -> BoardState {
         let piece = self.get_piece_at_position(position);
+        let captured_piece = self.get_piece_at_position(new_position);
         let mut new_state = self.set_piece_at_position(new_position, piece).set_piece_at_position(position, NOTHING);
-        if new_position == self.en_passant_square {
+        let is_en_passant_capture = piece & PIECE_MASK == PAWN && new_position == self.en_passant_square;
+        if is_en_passant_capture {
             let enemy_pawn_position = Position {
                 row: position.row,
                 col: new_position.col,
@@ -221,7 +362,12 @@ impl BoardState {
         if self.color_to_move == BLACK {
             new_state.full_move_clock = self.full_move_clock + 1
         }
-        //todo change half move counter
+        // pawn moves and captures reset the half move clock
+        if piece & PIECE_MASK == PAWN || captured_piece != NOTHING {
+            new_state.half_move_clock = 0;
+        } else {
+            new_state.half_move_clock = self.half_move_clock + 1;
+        }
         if piece & PIECE_MASK == KING {
             //castle both king and rook
             if new_position.col - position.col == 2 {
@@ -240,23 +386,22 @@ impl BoardState {
                 new_state.castling_rights = new_state.castling_rights.replace(char, "");
             }
         }
-        //remove castling rights for respective side
-        if piece & PIECE_MASK == ROOK {
-            if position.row == 0 && position.col == 0 {
-                new_state.castling_rights = self.castling_rights.replace("Q", "");
-            }
-            if position.row == 0 && position.col == 7 {
-                new_state.castling_rights = self.castling_rights.replace("K", "");
-            }
-            if position.row == 7 && position.col == 0 {
-                new_state.castling_rights = self.castling_rights.replace("q", "");
-            }
-            if position.row == 7 && position.col == 7 {
-                new_state.castling_rights = new_state.castling_rights.replace("k", "");
-            }
+        //remove castling rights for respective side, both when the rook moves away and when it gets captured
+        for corner in [position, new_position] {
+            let castling_char = match (corner.row, corner.col) {
+                (0, 0) => "Q",
+                (0, 7) => "K",
+                (7, 0) => "q",
+                (7, 7) => "k",
+                _ => continue,
+            };
+            new_state.castling_rights = new_state.castling_rights.replace(castling_char, "");
+        }
+        if new_state.castling_rights.is_empty() {
+            new_state.castling_rights = String::from("-");
         }
         // set en-passant square
-        if piece & PAWN > 0 && (position.row - new_position.row).abs() == 2 {
+        if piece & PIECE_MASK == PAWN && (position.row - new_position.row).abs() == 2 {
             let en_passant_position = Position {
                 row: (position.row + new_position.row) / 2,
                 col: position.col,
@@ -267,27 +412,560 @@ impl BoardState {
         }
         //change turn
         new_state.color_to_move = if self.color_to_move == WHITE { BLACK } else { WHITE };
+        new_state.zobrist_key ^= self.state_key() ^ new_state.state_key();
+        return new_state;
+    }
+
+    /// performs a move including promotion, the move is expected to be legal
+    pub fn make_move(&self, chess_move: Move) -> BoardState {
+        let mut new_state = self.perform_move(chess_move.from, chess_move.to);
+        if chess_move.promotion != NOTHING {
+            new_state = new_state.set_piece_at_position(chess_move.to, chess_move.promotion | self.color_to_move);
+        }
+        return new_state;
+    }
+
+    /// passes the turn to the opponent, not a legal move but used by the search to measure the threats of the opponent
+    pub fn make_null_move(&self) -> BoardState {
+        let mut new_state = self.clone();
+        if self.color_to_move == BLACK {
+            new_state.full_move_clock = self.full_move_clock + 1
+        }
+        new_state.half_move_clock = self.half_move_clock + 1;
+        new_state.en_passant_square = INVALID_POSITION;
+        new_state.color_to_move = opposite_color(self.color_to_move);
+        new_state.zobrist_key ^= self.state_key() ^ new_state.state_key();
         return new_state;
     }
 
-    pub fn get_legal_moves(&self) -> Vec<(Position, Position)> {
-        let mut moves: Vec<(Position, Position)> = Vec::new();
+    /// the same position with the colors swapped and the board flipped vertically, white's pieces become black's
+    pub fn mirrored(&self) -> BoardState {
+        let mut board = [NOTHING; 64];
+        for (square, piece) in self.board.iter().enumerate() {
+            if *piece != NOTHING {
+                board[square ^ 56] = (*piece & PIECE_MASK) | opposite_color(*piece & COLOR_MASK);
+            }
+        }
+        let castling_rights = self
+            .castling_rights
+            .chars()
+            .map(|c| {
+                if c.is_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                }
+            })
+            .collect();
+        let en_passant_square = if self.en_passant_square == INVALID_POSITION {
+            INVALID_POSITION
+        } else {
+            Position {
+                row: 7 - self.en_passant_square.row,
+                col: self.en_passant_square.col,
+            }
+        };
+        let mut bs = BoardState {
+            board,
+            color_to_move: opposite_color(self.color_to_move),
+            castling_rights,
+            en_passant_square,
+            half_move_clock: self.half_move_clock,
+            full_move_clock: self.full_move_clock,
+            zobrist_key: 0,
+        };
+        bs.zobrist_key = bs.compute_zobrist_key();
+        return bs;
+    }
+
+    pub fn get_legal_moves(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::new();
         for square in 0..64 {
             if self.board[square] & COLOR_MASK != self.color_to_move {
                 continue;
             }
             let piece_position = Position::position_from_indices(square);
             let target_squares = self.get_piece_moves_respecting_checks(piece_position);
+            let is_pawn = self.board[square] & PIECE_MASK == PAWN;
             for target_square in target_squares {
-                let move_tuple = (piece_position, target_square);
-                moves.push(move_tuple);
+                if is_pawn && (target_square.row == 0 || target_square.row == 7) {
+                    for promotion in [QUEEN, ROOK, BISHOP, KNIGHT] {
+                        moves.push(Move {
+                            from: piece_position,
+                            to: target_square,
+                            promotion,
+                        });
+                    }
+                } else {
+                    moves.push(Move::new(piece_position, target_square));
+                }
             }
         }
         return moves;
     }
 
+    /// every position this one can be reached from with a legal move, together with the move, including taken back
+    /// captures, promotions, en passant and castling. Castling rights and en passant squares the move could have
+    /// removed give extra predecessors, the clocks are only a guess
+    pub fn unmoves(&self) -> Vec<(BoardState, Move)> {
+        let mover = opposite_color(self.color_to_move);
+        let opponent = self.color_to_move;
+        let forward = if mover == WHITE { 1 } else { -1 };
+        let home_row = if mover == WHITE { 0 } else { 7 };
+        let last_row = 7 - home_row;
+        let pieces = [QUEEN, ROOK, BISHOP, KNIGHT];
+        let is_free = |position: Position| !is_position_out_of_bounds(position) && self.get_piece_at_position(position) == NOTHING;
+        let mut unmoves = Vec::new();
+        for square in 0..64 {
+            let piece = self.board[square];
+            if piece & COLOR_MASK != mover {
+                continue;
+            }
+            let to = Position::position_from_indices(square);
+            // the move, what it captured and whether the capture was en passant
+            let mut candidates: Vec<(Move, i32, bool)> = Vec::new();
+            let all_captures = |can_be_pawn: bool| -> Vec<i32> {
+                let mut captures = vec![NOTHING];
+                captures.extend(pieces.iter().map(|kind| kind | opponent));
+                if can_be_pawn {
+                    captures.push(PAWN | opponent);
+                }
+                return captures;
+            };
+            if piece & PIECE_MASK == PAWN {
+                let one_back = Position {
+                    row: to.row - forward,
+                    col: to.col,
+                };
+                if is_free(one_back) && one_back.row != home_row {
+                    candidates.push((Move::new(one_back, to), NOTHING, false));
+                    let two_back = Position {
+                        row: to.row - 2 * forward,
+                        col: to.col,
+                    };
+                    if two_back.row == home_row + forward && is_free(two_back) {
+                        candidates.push((Move::new(two_back, to), NOTHING, false));
+                    }
+                }
+                for col_step in [-1, 1] {
+                    let from = Position {
+                        row: to.row - forward,
+                        col: to.col + col_step,
+                    };
+                    if !is_free(from) || from.row == home_row {
+                        continue;
+                    }
+                    for captured in all_captures(true).into_iter().skip(1) {
+                        candidates.push((Move::new(from, to), captured, false));
+                    }
+                    // the captured pawn stood next to the one that took it, it came from two squares further
+                    let captured_pawn = Position { row: from.row, col: to.col };
+                    let pawn_origin = Position {
+                        row: to.row + forward,
+                        col: to.col,
+                    };
+                    if to.row == last_row - 2 * forward && is_free(captured_pawn) && is_free(pawn_origin) {
+                        candidates.push((Move::new(from, to), PAWN | opponent, true));
+                    }
+                }
+            } else {
+                if to.row == last_row && piece & PIECE_MASK != KING {
+                    for col_step in [-1, 0, 1] {
+                        let from = Position {
+                            row: to.row - forward,
+                            col: to.col + col_step,
+                        };
+                        if !is_free(from) {
+                            continue;
+                        }
+                        let captures = if col_step == 0 {
+                            vec![NOTHING]
+                        } else {
+                            all_captures(false).into_iter().skip(1).collect()
+                        };
+                        for captured in captures {
+                            candidates.push((
+                                Move {
+                                    from,
+                                    to,
+                                    promotion: piece & PIECE_MASK,
+                                },
+                                captured,
+                                false,
+                            ));
+                        }
+                    }
+                }
+                let origins = self.get_piece_moves_ignoring_checks(to).into_iter().filter(|from| is_free(*from));
+                for from in origins {
+                    // castling moves are taken back below
+                    if piece & PIECE_MASK == KING && (from.col - to.col).abs() == 2 {
+                        continue;
+                    }
+                    for captured in all_captures(to.row != 0 && to.row != 7) {
+                        candidates.push((Move::new(from, to), captured, false));
+                    }
+                }
+                if piece & PIECE_MASK == KING && to.row == home_row {
+                    for (king_col, rook_col, corner_col) in [(6, 5, 7), (2, 3, 0)] {
+                        let rook = Position {
+                            row: home_row,
+                            col: rook_col,
+                        };
+                        let corner = Position {
+                            row: home_row,
+                            col: corner_col,
+                        };
+                        let king_origin = Position { row: home_row, col: 4 };
+                        if to.col == king_col && self.get_piece_at_position(rook) == ROOK | mover && is_free(corner) && is_free(king_origin) {
+                            candidates.push((Move::new(king_origin, to), NOTHING, false));
+                        }
+                    }
+                }
+            }
+            for (chess_move, captured, en_passant) in candidates {
+                let previous = self.take_back(chess_move, captured, en_passant);
+                // taking back a capture or a promotion can give a side more material than a game can have
+                if !previous.has_possible_material() {
+                    continue;
+                }
+                for previous in previous.with_lost_rights(en_passant) {
+                    if self.is_reached_from(&previous, chess_move) {
+                        unmoves.push((previous, chess_move));
+                    }
+                }
+            }
+        }
+        return unmoves;
+    }
+
+    /// at most 8 pawns of each color and no more extra pieces than its missing pawns could have promoted to, bishops
+    /// count by the color of their squares
+    fn has_possible_material(&self) -> bool {
+        for color in [WHITE, BLACK] {
+            // the squares of the pieces, dark squares are 0 and light squares 1
+            let squares = |piece: i32| -> Vec<usize> {
+                return (0..64)
+                    .filter(|square| self.board[*square] == piece | color)
+                    .map(|square| (square / 8 + square % 8) % 2)
+                    .collect();
+            };
+            let extra = |piece: i32, start: usize| squares(piece).len().saturating_sub(start);
+            let bishops = squares(BISHOP);
+            let extra_bishops = (0..2).map(|square_color| bishops.iter().filter(|color| **color == square_color).count().saturating_sub(1));
+            let promoted = extra(QUEEN, 1) + extra(ROOK, 2) + extra(KNIGHT, 2) + extra_bishops.sum::<usize>();
+            if squares(PAWN).len() + promoted > 8 {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// the position before the move, the rook of a castling move goes back to its corner
+    fn take_back(&self, chess_move: Move, captured: i32, en_passant: bool) -> BoardState {
+        let mover = opposite_color(self.color_to_move);
+        let mut previous = self.clone();
+        let moved = if chess_move.promotion != NOTHING {
+            PAWN | mover
+        } else {
+            self.get_piece_at_position(chess_move.to)
+        };
+        previous.board[Position::index_from_position(chess_move.from)] = moved;
+        previous.board[Position::index_from_position(chess_move.to)] = if en_passant { NOTHING } else { captured };
+        if en_passant {
+            previous.board[Position::index_from_position(Position {
+                row: chess_move.from.row,
+                col: chess_move.to.col,
+            })] = captured;
+            previous.en_passant_square = chess_move.to;
+        } else {
+            previous.en_passant_square = INVALID_POSITION;
+        }
+        if moved & PIECE_MASK == KING && (chess_move.to.col - chess_move.from.col).abs() == 2 {
+            let (rook_col, corner_col) = if chess_move.to.col == 6 { (5, 7) } else { (3, 0) };
+            previous.board[Position::index_from_position(Position {
+                row: chess_move.to.row,
+                col: rook_col,
+            })] = NOTHING;
+            previous.board[Position::index_from_position(Position {
+                row: chess_move.to.row,
+                col: corner_col,
+            })] = ROOK | mover;
+        }
+        previous.color_to_move = mover;
+        let is_zeroing = moved & PIECE_MASK == PAWN || captured != NOTHING;
+        previous.half_move_clock = if is_zeroing { 0 } else { self.half_move_clock.saturating_sub(1) };
+        if mover == BLACK {
+            previous.full_move_clock = self.full_move_clock.saturating_sub(1).max(1);
+        }
+        previous.zobrist_key = previous.compute_zobrist_key();
+        return previous;
+    }
+
+    /// the position with every combination of castling rights its kings and rooks allow on top of its own, and
+    /// without an en passant square or with any the opponent's last move could have left
+    fn with_lost_rights(&self, en_passant: bool) -> Vec<BoardState> {
+        // a right to castle needs the king and the rook on their squares
+        let is_possible = |castling_char: char| -> bool {
+            let (color, row) = if castling_char.is_uppercase() { (WHITE, 0) } else { (BLACK, 7) };
+            let corner_col = if castling_char.eq_ignore_ascii_case(&'k') { 7 } else { 0 };
+            return self.get_piece_at_position(Position { row, col: 4 }) == KING | color
+                && self.get_piece_at_position(Position { row, col: corner_col }) == ROOK | color;
+        };
+        if self
+            .castling_rights
+            .chars()
+            .any(|castling_char| "KQkq".contains(castling_char) && !is_possible(castling_char))
+        {
+            return Vec::new();
+        }
+        let restorable: Vec<char> = "KQkq"
+            .chars()
+            .filter(|castling_char| !self.castling_rights.contains(*castling_char) && is_possible(*castling_char))
+            .collect();
+        let mut en_passant_squares = vec![self.en_passant_square];
+        if !en_passant {
+            // a pawn of the side that isn't to move may just have moved two squares
+            let other = opposite_color(self.color_to_move);
+            let (pawn_row, skipped_row, start_row) = if other == WHITE { (3, 2, 1) } else { (4, 5, 6) };
+            for col in 0..8 {
+                let is_free = |row: i32| self.get_piece_at_position(Position { row, col }) == NOTHING;
+                if self.get_piece_at_position(Position { row: pawn_row, col }) == PAWN | other && is_free(skipped_row) && is_free(start_row) {
+                    en_passant_squares.push(Position { row: skipped_row, col });
+                }
+            }
+        }
+        let mut variants = Vec::new();
+        for subset in 0..(1 << restorable.len()) {
+            let rights: String = "KQkq"
+                .chars()
+                .filter(|castling_char| {
+                    let restored = restorable
+                        .iter()
+                        .position(|restorable_char| restorable_char == castling_char)
+                        .is_some_and(|i| subset & (1 << i) != 0);
+                    return self.castling_rights.contains(*castling_char) || restored;
+                })
+                .collect();
+            for en_passant_square in &en_passant_squares {
+                let mut variant = self.clone();
+                variant.castling_rights = if rights.is_empty() { String::from("-") } else { rights.clone() };
+                variant.en_passant_square = *en_passant_square;
+                variant.zobrist_key ^= self.state_key() ^ variant.state_key();
+                variants.push(variant);
+            }
+        }
+        return variants;
+    }
+
+    /// whether the move is legal in the legal position before and leads to this one
+    fn is_reached_from(&self, previous: &BoardState, chess_move: Move) -> bool {
+        if previous.is_color_in_check(self.color_to_move) || !previous.is_legal_move(chess_move) {
+            return false;
+        }
+        let next = previous.make_move(chess_move);
+        let rights = |castling_rights: &str| -> String { "KQkq".chars().filter(|castling_char| castling_rights.contains(*castling_char)).collect() };
+        return next.board == self.board
+            && next.en_passant_square == self.en_passant_square
+            && rights(&next.castling_rights) == rights(&self.castling_rights);
+    }
+
+    /// finds the legal move written in coordinate notation like "e2e4" or "e7e8q"
+    pub fn move_from_string(&self, input: &str) -> Option<Move> {
+        let input = input.trim().to_lowercase();
+        return self.get_legal_moves().into_iter().find(|m| m.str() == input);
+    }
+
+    /// finds the legal move written in standard algebraic notation as in PGN files, e.g. "Nf3", "exd5", "O-O" or "e8=Q+"
+    pub fn move_from_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let king_row = if self.color_to_move == WHITE { 0 } else { 7 };
+        let castling_col = match san {
+            "O-O" | "0-0" => Some(6),
+            "O-O-O" | "0-0-0" => Some(2),
+            _ => None,
+        };
+        if let Some(col) = castling_col {
+            let castling_move = Move::new(Position { row: king_row, col: 4 }, Position { row: king_row, col });
+            let is_king = self.get_piece_at_position(castling_move.from) & PIECE_MASK == KING;
+            return if is_king && self.is_legal_move(castling_move) {
+                Some(castling_move)
+            } else {
+                None
+            };
+        }
+        // the promotion piece follows the target square, usually with a "="
+        let (san, promotion) = match san.char_indices().last() {
+            Some((index, piece @ ('Q' | 'R' | 'B' | 'N'))) if index > 1 => (san[..index].trim_end_matches('='), piece_from_char(piece) & PIECE_MASK),
+            _ => (san, NOTHING),
+        };
+        let (piece, rest) = match san.chars().next() {
+            Some(piece @ ('N' | 'B' | 'R' | 'Q' | 'K')) => (piece_from_char(piece) & PIECE_MASK, &san[1..]),
+            _ => (PAWN, san),
+        };
+        let squares: Vec<char> = rest.chars().filter(|c| *c != 'x' && *c != ':' && *c != '-').collect();
+        if squares.len() < 2 {
+            return None;
+        }
+        let (hints, target) = squares.split_at(squares.len() - 2);
+        let target_col = target[0] as i32 - 'a' as i32;
+        let target_row = target[1] as i32 - '1' as i32;
+        if !(0..8).contains(&target_col) || !(0..8).contains(&target_row) {
+            return None;
+        }
+        // the file or row of the start square, for moves more than one piece could make
+        let matches_hints = |from: Position| {
+            return hints.iter().all(|hint| match hint {
+                'a'..='h' => from.col == *hint as i32 - 'a' as i32,
+                '1'..='8' => from.row == *hint as i32 - '1' as i32,
+                _ => false,
+            });
+        };
+        let mut candidates = self.get_legal_moves().into_iter().filter(|m| {
+            return self.get_piece_at_position(m.from) & PIECE_MASK == piece
+                && m.to.col == target_col
+                && m.to.row == target_row
+                && m.promotion == promotion
+                && matches_hints(m.from);
+        });
+        let chess_move = candidates.next()?;
+        // an ambiguous move doesn't say which piece moves
+        if candidates.next().is_some() {
+            return None;
+        }
+        return Some(chess_move);
+    }
+
+    /// whether the move can be played in this position, e.g. for checking moves stored in the transposition table
+    pub fn is_legal_move(&self, chess_move: Move) -> bool {
+        let piece = self.get_piece_at_position(chess_move.from);
+        if piece & COLOR_MASK != self.color_to_move {
+            return false;
+        }
+        let is_promotion = piece & PIECE_MASK == PAWN && (chess_move.to.row == 0 || chess_move.to.row == 7);
+        if is_promotion != (chess_move.promotion != NOTHING) {
+            return false;
+        }
+        return self.get_piece_moves_respecting_checks(chess_move.from).contains(&chess_move.to);
+    }
+
     pub fn is_checkmate(&self) -> bool {
-        return self.get_legal_moves().len() == 0 && self.is_color_in_check(self.color_to_move);
+        return self.get_legal_moves().is_empty() && self.is_color_in_check(self.color_to_move);
+    }
+
+    pub fn is_in_check(&self) -> bool {
+        return self.is_color_in_check(self.color_to_move);
+    }
+
+    pub fn color_to_move(&self) -> i32 {
+        return self.color_to_move;
+    }
+
+    pub fn half_move_clock(&self) -> u32 {
+        return self.half_move_clock;
+    }
+
+    /// whether either side may still castle
+    pub fn has_castling_rights(&self) -> bool {
+        return self.castling_rights.chars().any(|castling_char| "KQkq".contains(castling_char));
+    }
+
+    /// returns whether the move takes a piece, including en-passant
+    pub fn is_capture(&self, chess_move: Move) -> bool {
+        if self.get_piece_at_position(chess_move.to) != NOTHING {
+            return true;
+        }
+        return self.get_piece_at_position(chess_move.from) & PIECE_MASK == PAWN && chess_move.to == self.en_passant_square;
+    }
+
+    /// hash of the position, equal positions (ignoring the clocks) have equal keys
+    pub fn zobrist_key(&self) -> u64 {
+        return self.zobrist_key;
+    }
+
+    fn compute_zobrist_key(&self) -> u64 {
+        let mut key = 0;
+        for square in 0..64 {
+            let piece = self.board[square];
+            if piece != NOTHING {
+                key ^= zobrist::piece_key(piece, square);
+            }
+        }
+        return key ^ self.state_key();
+    }
+
+    /// the part of the key for the castling rights, the en passant square and the side to move
+    fn state_key(&self) -> u64 {
+        let mut key = 0;
+        for (i, castling_char) in ["K", "Q", "k", "q"].iter().enumerate() {
+            if self.castling_rights.contains(castling_char) {
+                key ^= zobrist::CASTLING_KEYS[i];
+            }
+        }
+        if self.en_passant_square != INVALID_POSITION {
+            key ^= zobrist::EN_PASSANT_KEYS[self.en_passant_square.col as usize];
+        }
+        if self.color_to_move == BLACK {
+            key ^= zobrist::BLACK_TO_MOVE_KEY;
+        }
+        return key;
+    }
+
+    /// hash of the pawns only, positions with the same pawn structure have equal keys
+    pub fn pawn_key(&self) -> u64 {
+        let mut key = 0;
+        for square in 0..64 {
+            let piece = self.board[square];
+            if piece & PIECE_MASK == PAWN {
+                key ^= zobrist::piece_key(piece, square);
+            }
+        }
+        return key;
+    }
+
+    /// the key Polyglot opening books use for the position, it has its own keys and only counts the en-passant
+    /// square when a pawn of the side to move stands next to the pawn that just moved
+    pub fn polyglot_key(&self) -> u64 {
+        let mut key = 0;
+        for square in 0..64 {
+            let piece = self.board[square];
+            if piece == NOTHING {
+                continue;
+            }
+            let kind = match piece & PIECE_MASK {
+                PAWN => 0,
+                KNIGHT => 1,
+                BISHOP => 2,
+                ROOK => 3,
+                QUEEN => 4,
+                _ => 5,
+            };
+            let is_white = (piece & WHITE != 0) as usize;
+            key ^= polyglot_keys::RANDOM64[64 * (2 * kind + is_white) + square];
+        }
+        for (i, castling_char) in ["K", "Q", "k", "q"].iter().enumerate() {
+            if self.castling_rights.contains(castling_char) {
+                key ^= polyglot_keys::RANDOM64[768 + i];
+            }
+        }
+        if self.en_passant_square != INVALID_POSITION {
+            let pawn_row = if self.color_to_move == WHITE {
+                self.en_passant_square.row - 1
+            } else {
+                self.en_passant_square.row + 1
+            };
+            let can_capture = [self.en_passant_square.col - 1, self.en_passant_square.col + 1]
+                .into_iter()
+                .filter(|col| (0..8).contains(col))
+                .any(|col| self.get_piece_at_position(Position { row: pawn_row, col }) == PAWN | self.color_to_move);
+            if can_capture {
+                key ^= polyglot_keys::RANDOM64[772 + self.en_passant_square.col as usize];
+            }
+        }
+        if self.color_to_move == WHITE {
+            key ^= polyglot_keys::RANDOM64[780];
+        }
+        return key;
     }
 
     fn is_color_in_check(&self, color: i32) -> bool {
@@ -301,13 +979,20 @@ impl BoardState {
     }
 
     /// returns piece integer at the given position, position must be in bounds
-    fn get_piece_at_position(&self, position: Position) -> i32 {
+    pub fn get_piece_at_position(&self, position: Position) -> i32 {
         return self.board[Position::index_from_position(position)];
     }
 
     fn set_piece_at_position(&self, position: Position, new_piece: i32) -> BoardState {
+        let index = Position::index_from_position(position);
         let mut new_board = self.board;
-        new_board[Position::index_from_position(position)] = new_piece;
+        new_board[index] = new_piece;
+        let mut zobrist_key = self.zobrist_key;
+        for piece in [self.board[index], new_piece] {
+            if piece != NOTHING {
+                zobrist_key ^= zobrist::piece_key(piece, index);
+            }
+        }
         let new_state = BoardState {
             board: new_board,
             color_to_move: self.color_to_move,
@@ -315,10 +1000,55 @@ impl BoardState {
             castling_rights: String::from(&self.castling_rights),
             half_move_clock: self.half_move_clock,
             full_move_clock: self.full_move_clock,
+            zobrist_key,
         };
         return new_state;
     }
 
+    /// static exchange evaluation: the material the side to move wins on the target square when both sides keep
+    /// recapturing with their least valuable piece and may stop whenever continuing would lose material
+    pub fn see(&self, chess_move: Move) -> i32 {
+        let mut board = self.board;
+        let mut gains: Vec<i32> = Vec::new();
+        let moving_piece = self.get_piece_at_position(chess_move.from);
+        let mut piece_on_square = moving_piece;
+        let mut first_gain = PIECE_VALUES[(self.get_piece_at_position(chess_move.to) & PIECE_MASK) as usize];
+        if moving_piece & PIECE_MASK == PAWN && chess_move.to == self.en_passant_square {
+            first_gain = PIECE_VALUES[PAWN as usize];
+            board[Position::index_from_position(Position {
+                row: chess_move.from.row,
+                col: chess_move.to.col,
+            })] = NOTHING;
+        }
+        if chess_move.promotion != NOTHING {
+            first_gain += PIECE_VALUES[chess_move.promotion as usize] - PIECE_VALUES[PAWN as usize];
+            piece_on_square = chess_move.promotion | (moving_piece & COLOR_MASK);
+        }
+        gains.push(first_gain);
+        board[Position::index_from_position(chess_move.from)] = NOTHING;
+        let mut color = opposite_color(moving_piece & COLOR_MASK);
+        // removing a piece from the board uncovers the sliders behind it, so the attackers are searched again every time
+        while let Some(attacker_position) = least_valuable_attacker(&board, chess_move.to, color) {
+            let attacker = board[Position::index_from_position(attacker_position)];
+            if attacker & PIECE_MASK == KING && least_valuable_attacker(&board, chess_move.to, opposite_color(color)).is_some() {
+                // the king can't capture a defended piece
+                break;
+            }
+            let previous_gain = gains[gains.len() - 1];
+            gains.push(PIECE_VALUES[(piece_on_square & PIECE_MASK) as usize] - previous_gain);
+            board[Position::index_from_position(attacker_position)] = NOTHING;
+            piece_on_square = attacker;
+            color = opposite_color(color);
+        }
+        // going backwards every side decides whether capturing is better than stopping
+        while gains.len() > 1 {
+            let last_gain = gains.pop().expect("gains can't be empty");
+            let previous = gains.len() - 1;
+            gains[previous] = -(-gains[previous]).max(last_gain);
+        }
+        return gains[0];
+    }
+
     fn is_position_attacked(&self, position_to_be_checked: Position) -> bool {
         let color = COLOR_MASK & self.get_piece_at_position(position_to_be_checked);
         for row in 0..8 {
@@ -338,7 +1068,8 @@ impl BoardState {
         return false;
     }
 
-    fn get_piece_moves_ignoring_checks(&self, position: Position) -> Vec<Position> {
+    /// the squares the piece could move to if its own king didn't matter, for a pawn its pushes and captures
+    pub(crate) fn get_piece_moves_ignoring_checks(&self, position: Position) -> Vec<Position> {
         let piece = self.get_piece_at_position(position);
         let colorless_piece = piece & PIECE_MASK;
         let moves = match colorless_piece {
@@ -455,7 +1186,7 @@ impl BoardState {
                 if is_opposite_color(piece, piece_at_new_position) {
                     break;
                 }
-                length = length + 1;
+                length += 1;
                 previous_position_checked = new_position;
             }
         }
@@ -519,7 +1250,7 @@ impl BoardState {
                     col: position.col + column,
                 });
                 if position_i_squares_to_the_side != NOTHING {
-                    break 'castling_direction;
+                    continue 'castling_direction;
                 }
             }
             moves.push(Position {
@@ -569,15 +1300,15 @@ fn fen_to_board(board_string: &&str) -> [i32; 64] {
     let mut board = [0; 64];
     for ch in board_string.chars() {
         match ch {
-            '1'..='8' => col = col + (ch.to_digit(10).expect("Invalid number parsing FEN") as i32),
+            '1'..='8' => col += ch.to_digit(10).expect("Invalid number parsing FEN") as i32,
             'r' | 'n' | 'b' | 'q' | 'k' | 'p' | 'R' | 'N' | 'B' | 'Q' | 'K' | 'P' => {
                 let piece = piece_from_char(ch);
                 let index = (rank * 8 + col) as usize;
                 board[index] = piece;
-                col = col + 1;
+                col += 1;
             }
             '/' => {
-                rank = rank - 1;
+                rank -= 1;
                 col = 0;
             }
             _ => println!("Unknown fen char: {}", ch),
@@ -612,6 +1343,80 @@ fn is_same_color(piece_1: i32, piece_2: i32) -> bool {
     return col_1 == col_2;
 }
 
+fn opposite_color(color: i32) -> i32 {
+    return if color == WHITE { BLACK } else { WHITE };
+}
+
+/// the position of the cheapest piece of the given color attacking the square on the given board
+fn least_valuable_attacker(board: &[i32; 64], target: Position, color: i32) -> Option<Position> {
+    let piece_at = |position: Position| -> i32 {
+        if is_position_out_of_bounds(position) {
+            return NOTHING;
+        }
+        return board[Position::index_from_position(position)];
+    };
+    let mut best: Option<Position> = None;
+    let mut best_value = i32::MAX;
+    let mut consider = |position: Position, piece: i32| {
+        let value = PIECE_VALUES[(piece & PIECE_MASK) as usize];
+        if value < best_value {
+            best_value = value;
+            best = Some(position);
+        }
+    };
+    // pawns attack the target from one row behind it, seen from their direction of movement
+    let pawn_row = if color == WHITE { target.row - 1 } else { target.row + 1 };
+    for col_step in [-1, 1] {
+        let position = Position {
+            row: pawn_row,
+            col: target.col + col_step,
+        };
+        if piece_at(position) == PAWN | color {
+            consider(position, PAWN);
+        }
+    }
+    for (row_step, col_step) in [(1, 2), (2, 1), (-1, 2), (-2, 1), (1, -2), (2, -1), (-1, -2), (-2, -1)] {
+        let position = Position {
+            row: target.row + row_step,
+            col: target.col + col_step,
+        };
+        if piece_at(position) == KNIGHT | color {
+            consider(position, KNIGHT);
+        }
+    }
+    for (row_step, col_step) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
+        let is_diagonal = row_step != 0 && col_step != 0;
+        let mut position = target;
+        loop {
+            position = Position {
+                row: position.row + row_step,
+                col: position.col + col_step,
+            };
+            if is_position_out_of_bounds(position) {
+                break;
+            }
+            let piece = piece_at(position);
+            if piece == NOTHING {
+                continue;
+            }
+            let colorless_piece = piece & PIECE_MASK;
+            let is_slider = colorless_piece == QUEEN || colorless_piece == if is_diagonal { BISHOP } else { ROOK };
+            if piece & COLOR_MASK == color && is_slider {
+                consider(position, colorless_piece);
+            }
+            break;
+        }
+        let king_position = Position {
+            row: target.row + row_step,
+            col: target.col + col_step,
+        };
+        if piece_at(king_position) == KING | color {
+            consider(king_position, KING);
+        }
+    }
+    return best;
+}
+
 fn is_position_out_of_bounds(position: Position) -> bool {
     if position.row < 0 || position.col < 0 || position.row > 7 || position.col > 7 {
         return true;
@@ -620,5 +1425,5 @@ fn is_position_out_of_bounds(position: Position) -> bool {
 }
 
 fn is_index_out_of_bounds(index: i32) -> bool {
-    return index < 0 || index > 63;
+    return !(0..=63).contains(&index);
 }
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::board_state::WHITE;
+use crate::pgn;
+use crate::polyglot::{self, BookEntry};
+use crate::selfplay::GameResult;
+
+pub const DEFAULT_MAX_PLIES: usize = 20;
+pub const DEFAULT_MIN_GAMES: u32 = 1;
+
+pub struct BookOptions {
+    /// moves after this many plies of a game aren't in the book
+    pub max_plies: usize,
+    /// moves played in fewer games are left out
+    pub min_games: u32,
+    /// only games with one of these results are used
+    pub results: Vec<GameResult>,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        return BookOptions {
+            max_plies: DEFAULT_MAX_PLIES,
+            min_games: DEFAULT_MIN_GAMES,
+            results: vec![GameResult::WhiteWins, GameResult::Draw, GameResult::BlackWins],
+        };
+    }
+}
+
+/// how the games with a move in a position ended, from the view of the side that played it
+#[derive(Default)]
+struct MoveStats {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl MoveStats {
+    fn games(&self) -> u32 {
+        return self.wins + self.draws + self.losses;
+    }
+
+    /// a win counts twice as much as a draw, a move that only lost gets weight 0 so it's never played
+    fn score(&self) -> u64 {
+        return 2 * self.wins as u64 + self.draws as u64;
+    }
+}
+
+/// reads the games of the PGN files and writes a Polyglot book with the moves played in them
+pub fn build(pgn_paths: &[String], output_path: &str, options: &BookOptions) -> Result<(), String> {
+    let mut stats: HashMap<(u64, u16), MoveStats> = HashMap::new();
+    let mut games_used = 0;
+    let mut games_skipped = 0;
+    for path in pgn_paths {
+        let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        for game in pgn::parse_games(&text) {
+            let Some(result) = game.result.filter(|result| options.results.contains(result)) else {
+                games_skipped += 1;
+                continue;
+            };
+            if let Some(error) = &game.error {
+                println!("{}: {}", path, error);
+            }
+            games_used += 1;
+            let mut bs = game.start.clone();
+            for chess_move in game.moves.iter().take(options.max_plies) {
+                let key = (bs.polyglot_key(), polyglot::encode_move(&bs, *chess_move));
+                let move_stats = stats.entry(key).or_default();
+                match (result, bs.color_to_move() == WHITE) {
+                    (GameResult::Draw, _) => move_stats.draws += 1,
+                    (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => move_stats.wins += 1,
+                    _ => move_stats.losses += 1,
+                }
+                bs = bs.make_move(*chess_move);
+            }
+        }
+    }
+    stats.retain(|_, move_stats| move_stats.games() >= options.min_games);
+    // the weights have 16 bits, with many games all of them are scaled down alike
+    let max_score = stats.values().map(|move_stats| move_stats.score()).max().unwrap_or(0);
+    let scale = (max_score as f64 / u16::MAX as f64).max(1.0);
+    let entries: Vec<BookEntry> = stats
+        .iter()
+        .map(|((key, raw_move), move_stats)| BookEntry {
+            key: *key,
+            raw_move: *raw_move,
+            // moves that scored anything keep a weight above 0 after scaling
+            weight: if move_stats.score() == 0 {
+                0
+            } else {
+                ((move_stats.score() as f64 / scale) as u16).max(1)
+            },
+            learn: 0,
+        })
+        .collect();
+    polyglot::write_book(output_path, &entries)?;
+    println!(
+        "{} games used, {} skipped for their result, {} book entries written to {}",
+        games_used,
+        games_skipped,
+        entries.len(),
+        output_path
+    );
+    return Ok(());
+}
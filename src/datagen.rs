@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::board_state::{BoardState, WHITE};
+use crate::move_ordering::is_tactical;
+use crate::search::{SearchLimits, Searcher, MATE_THRESHOLD};
+use crate::selfplay::{game_result, GameResult, MAX_GAME_PLIES};
+
+pub const DEFAULT_GAMES: u32 = 100;
+pub const DEFAULT_NODES: u64 = 5000;
+pub const DEFAULT_SEED: u64 = 1;
+// random moves from the starting position before the engines take over, so no two games are the same
+const RANDOM_OPENING_PLIES: usize = 8;
+
+/// a position the engine thought about during a game, the score is from white's perspective
+struct Sample {
+    fen: String,
+    score: i32,
+}
+
+/// plays games of the engine against itself and writes every quiet position as "<fen> | <score> | <result>",
+/// the result is 1.0 when white won, 0.5 for a draw and 0.0 when black won, the same seed always gives the same file
+pub fn generate(output_path: &str, games: u32, nodes: u64, seed: u64) -> Result<(), String> {
+    let file = File::create(output_path).map_err(|error| format!("Could not create {}: {}", output_path, error))?;
+    let mut writer = BufWriter::new(file);
+    let mut random = Random::new(seed);
+    let mut searcher = Searcher::new();
+    let limits = SearchLimits::nodes(nodes);
+    let mut positions = 0;
+    for game in 0..games {
+        let start = random_opening(&mut random);
+        searcher.clear();
+        let (result, samples) = play_game(&start, &mut searcher, &limits);
+        let result_value = match result {
+            GameResult::WhiteWins => 1.0,
+            GameResult::Draw => 0.5,
+            GameResult::BlackWins => 0.0,
+        };
+        for sample in &samples {
+            writeln!(writer, "{} | {} | {:.1}", sample.fen, sample.score, result_value).map_err(|error| error.to_string())?;
+        }
+        positions += samples.len();
+        println!("game {}/{}: {:?}, {} positions in total", game + 1, games, result, positions);
+    }
+    writer.flush().map_err(|error| error.to_string())?;
+    return Ok(());
+}
+
+/// only positions that aren't in the middle of a tactic are kept, their static evaluation means something
+fn play_game(start: &BoardState, searcher: &mut Searcher, limits: &SearchLimits) -> (GameResult, Vec<Sample>) {
+    let mut bs = start.clone();
+    let mut keys: Vec<u64> = Vec::new();
+    let mut samples = Vec::new();
+    loop {
+        if let Some(result) = game_result(&bs, &keys) {
+            return (result, samples);
+        }
+        if keys.len() > MAX_GAME_PLIES {
+            return (GameResult::Draw, samples);
+        }
+        searcher.set_game_history(keys.clone());
+        let search_result = searcher.search(&bs, limits);
+        let best_move = search_result.best_move.expect("a position that isn't over has a legal move");
+        if !bs.is_in_check() && !is_tactical(&bs, best_move) && search_result.score.abs() < MATE_THRESHOLD {
+            let score = if bs.color_to_move() == WHITE {
+                search_result.score
+            } else {
+                -search_result.score
+            };
+            samples.push(Sample { fen: bs.to_fen(), score });
+        }
+        keys.push(bs.zobrist_key());
+        bs = bs.make_move(best_move);
+    }
+}
+
+/// random legal moves from the starting position, a line that already ends the game is thrown away
+fn random_opening(random: &mut Random) -> BoardState {
+    loop {
+        let mut bs = BoardState::new();
+        for _ in 0..RANDOM_OPENING_PLIES {
+            let moves = bs.get_legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            bs = bs.make_move(moves[random.next() as usize % moves.len()]);
+        }
+        if game_result(&bs, &[]).is_none() {
+            return bs;
+        }
+    }
+}
+
+/// xorshift64*, the same generator as for the zobrist keys
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        // a state of 0 would stay 0 forever
+        return Random { state: seed.max(1) };
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        return self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+    }
+}
@@ -0,0 +1,217 @@
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board_state::{BoardState, Move};
+use crate::polyglot::{Book, BookSelection};
+use crate::search::{IterationCallback, SearchClock, SearchLimits, SearchResult, Searcher};
+use crate::selfplay::{game_result, GameResult};
+
+// time kept back on every move for talking to the GUI
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+// without a number of moves until the next time control the time left is spread over this many moves
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// called with the result once a search has finished
+pub type ResultCallback = Box<dyn FnOnce(SearchResult) + Send>;
+
+/// the game and the searcher behind the protocols, searches run on their own thread so commands can still be read
+pub struct Engine {
+    // the searcher is moved to the search thread while a search runs
+    searcher: Option<Searcher>,
+    search_thread: Option<JoinHandle<Searcher>>,
+    stop: Arc<AtomicBool>,
+    clock: Arc<SearchClock>,
+    // a search that holds back its result waits until this is sent
+    release: Option<Sender<()>>,
+    book: Option<Book>,
+    use_book: bool,
+    book_selection: BookSelection,
+    bs: BoardState,
+    // the positions of the game before the current one
+    history: Vec<BoardState>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        return Engine::new();
+    }
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        let searcher = Searcher::new();
+        return Engine {
+            stop: searcher.stop_flag(),
+            clock: searcher.clock(),
+            searcher: Some(searcher),
+            search_thread: None,
+            release: None,
+            book: None,
+            use_book: false,
+            book_selection: BookSelection::WeightedRandom,
+            bs: BoardState::new(),
+            history: Vec::new(),
+        };
+    }
+
+    pub fn position(&self) -> &BoardState {
+        return &self.bs;
+    }
+
+    /// starts a new game from the position
+    pub fn set_position(&mut self, bs: BoardState) {
+        self.bs = bs;
+        self.history.clear();
+    }
+
+    pub fn make_move(&mut self, chess_move: Move) {
+        let new_state = self.bs.make_move(chess_move);
+        self.history.push(mem::replace(&mut self.bs, new_state));
+    }
+
+    /// the number of moves played since the position was set
+    pub fn moves_played(&self) -> usize {
+        return self.history.len();
+    }
+
+    /// the result if the game is over by the rules
+    pub fn game_result(&self) -> Option<GameResult> {
+        let keys: Vec<u64> = self.history.iter().map(|bs| bs.zobrist_key()).collect();
+        return game_result(&self.bs, &keys);
+    }
+
+    /// takes back the last move, false if there is none
+    pub fn undo(&mut self) -> bool {
+        return match self.history.pop() {
+            Some(bs) => {
+                self.bs = bs;
+                true
+            }
+            None => false,
+        };
+    }
+
+    /// forgets what was learned in earlier games
+    pub fn new_game(&mut self) {
+        self.searcher_mut().clear();
+    }
+
+    /// the book options are handled here, everything else by the searcher
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name.to_lowercase().as_str() {
+            "bookfile" => {
+                self.book = Some(Book::open(value.trim())?);
+                self.use_book = true;
+            }
+            "ownbook" => {
+                let use_book: bool = value.trim().parse().map_err(|_| format!("Not a valid value for {}: {}", name, value))?;
+                if use_book && self.book.is_none() {
+                    return Err(String::from("No book loaded, set BookFile first"));
+                }
+                self.use_book = use_book;
+            }
+            "bookbestmove" => {
+                let best_move: bool = value.trim().parse().map_err(|_| format!("Not a valid value for {}: {}", name, value))?;
+                self.book_selection = if best_move { BookSelection::Best } else { BookSelection::WeightedRandom };
+            }
+            _ => return self.searcher_mut().set_option(name, value),
+        }
+        return Ok(());
+    }
+
+    /// searches the current position on the search thread, the callbacks are called from there, a held back result is
+    /// only handed over after stop or ponderhit, even when the search finishes earlier
+    pub fn go(&mut self, limits: SearchLimits, hold_result: bool, on_iteration: IterationCallback, on_result: ResultCallback) {
+        self.stop();
+        // a book move is played right away, only searches that wait for the opponent don't use the book
+        if let Some(book_move) = self.book_move().filter(|_| !hold_result) {
+            on_result(SearchResult {
+                best_move: Some(book_move),
+                score: 0,
+                depth: 0,
+                nodes: 0,
+                hashfull: 0,
+                pv: vec![book_move],
+                lines: Vec::new(),
+            });
+            return;
+        }
+        let mut searcher = self.take_searcher();
+        searcher.set_iteration_callback(on_iteration);
+        searcher.set_game_history(self.history.iter().map(|bs| bs.zobrist_key()).collect());
+        let (release, released) = mpsc::channel();
+        self.release = if hold_result { Some(release) } else { None };
+        // a stop for the previous search may still be set
+        self.stop.store(false, Ordering::Relaxed);
+        if limits.ponder {
+            self.clock.pause();
+        }
+        let bs = self.bs.clone();
+        self.search_thread = Some(thread::spawn(move || {
+            let result = searcher.search(&bs, &limits);
+            // the sender is only dropped without sending when the result doesn't have to wait
+            let _ = released.recv();
+            on_result(result);
+            return searcher;
+        }));
+    }
+
+    /// stops the search and waits until its result has been handed over
+    pub fn stop(&mut self) {
+        if self.search_thread.is_none() {
+            return;
+        }
+        self.stop.store(true, Ordering::Relaxed);
+        self.release();
+        self.wait();
+    }
+
+    /// the opponent played the expected move, the pondering search goes on as a normal one with its clock running
+    pub fn ponderhit(&mut self) {
+        self.clock.start();
+        self.release();
+    }
+
+    /// waits until the running search has finished on its own
+    pub fn wait(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            self.searcher = Some(search_thread.join().expect("the search thread panicked"));
+        }
+    }
+
+    fn book_move(&self) -> Option<Move> {
+        if !self.use_book {
+            return None;
+        }
+        return self.book.as_ref()?.choose_move(&self.bs, self.book_selection);
+    }
+
+    fn release(&mut self) {
+        if let Some(release) = self.release.take() {
+            let _ = release.send(());
+        }
+    }
+
+    /// options and new games stop a running search first
+    fn searcher_mut(&mut self) -> &mut Searcher {
+        self.stop();
+        return self.searcher.as_mut().expect("the searcher is back once the search thread has finished");
+    }
+
+    fn take_searcher(&mut self) -> Searcher {
+        self.stop();
+        return self.searcher.take().expect("the searcher is back once the search thread has finished");
+    }
+}
+
+/// the time to spend on a move with the given time left on the clock and the increment per move
+pub fn time_for_move(time_left: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+    let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+    let time = time_left / moves_to_go + increment * 3 / 4;
+    let maximum = time_left.saturating_sub(MOVE_OVERHEAD);
+    return time.min(maximum).max(Duration::from_millis(1));
+}
@@ -0,0 +1,360 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+use crate::board_state::{BoardState, Position, BISHOP, BLACK, KING, KNIGHT, NOTHING, PAWN, PIECE_MASK, QUEEN, ROOK, WHITE};
+use crate::eval_parameters::DEFAULT_PARAMETERS;
+use crate::pawn_structure::{blocked_passed_pawns, evaluate_pawns, PawnEntry, PawnTable};
+
+// how much every piece counts towards the middlegame, all pieces on the board are a phase of 24
+const PHASE_WEIGHTS: [i32; 7] = [0, 0, 2, 1, 1, 4, 0];
+const MAX_PHASE: i32 = 24;
+
+// a single attacker is rarely dangerous, the more pieces join the attack the more the weights count
+const KING_ATTACKER_SCALE: [i32; 8] = [0, 0, 50, 75, 88, 94, 97, 99];
+
+/// a middlegame and an endgame score, interpolated by the phase of the game at the end
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Score {
+    pub middlegame: i32,
+    pub endgame: i32,
+}
+
+impl Score {
+    pub const fn new(middlegame: i32, endgame: i32) -> Score {
+        return Score { middlegame, endgame };
+    }
+
+    pub fn str(&self) -> String {
+        return format!("{} / {}", self.middlegame, self.endgame);
+    }
+
+    /// blends the middlegame and endgame scores, promotions can push the phase above the maximum
+    pub fn taper(&self, phase: i32) -> i32 {
+        let phase = phase.min(MAX_PHASE);
+        return (self.middlegame * phase + self.endgame * (MAX_PHASE - phase)) / MAX_PHASE;
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        return Score::new(self.middlegame + other.middlegame, self.endgame + other.endgame);
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        return Score::new(self.middlegame - other.middlegame, self.endgame - other.endgame);
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Score) {
+        *self = *self - other;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Term {
+    Material,
+    PieceSquares,
+    PawnStructure,
+    PassedPawns,
+    KingSafety,
+    Mobility,
+}
+
+pub const TERMS: [Term; 6] = [
+    Term::Material,
+    Term::PieceSquares,
+    Term::PawnStructure,
+    Term::PassedPawns,
+    Term::KingSafety,
+    Term::Mobility,
+];
+
+impl Term {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Term::Material => "Material",
+            Term::PieceSquares => "Piece squares",
+            Term::PawnStructure => "Pawn structure",
+            Term::PassedPawns => "Passed pawns",
+            Term::KingSafety => "King safety",
+            Term::Mobility => "Mobility",
+        };
+    }
+}
+
+/// every term of the evaluation for both colors, white first and each from its own point of view
+#[derive(Clone, Debug, Default)]
+pub struct EvalTrace {
+    scores: [[Score; 2]; TERMS.len()],
+    pub phase: i32,
+}
+
+impl EvalTrace {
+    pub fn score(&self, term: Term, color: i32) -> Score {
+        return self.scores[term as usize][side_index(color)];
+    }
+
+    /// what the term is worth for white once the two colors are weighed against each other
+    pub fn term_total(&self, term: Term) -> Score {
+        return self.scores[term as usize][0] - self.scores[term as usize][1];
+    }
+
+    /// the evaluation from white's perspective
+    pub fn total(&self) -> i32 {
+        let mut total = Score::default();
+        for term in TERMS {
+            total += self.term_total(term);
+        }
+        return total.taper(self.phase);
+    }
+
+    /// a table of all terms, middlegame and endgame score for every color and both weighed against each other
+    pub fn str(&self) -> String {
+        let mut result = format!("{:<16}{:>15}{:>15}{:>15}{:>9}\n", "Term", "White", "Black", "Total", "Tapered");
+        for term in TERMS {
+            result.push_str(&format!(
+                "{:<16}{:>15}{:>15}{:>15}{:>9}\n",
+                term.name(),
+                self.score(term, WHITE).str(),
+                self.score(term, BLACK).str(),
+                self.term_total(term).str(),
+                self.term_total(term).taper(self.phase)
+            ));
+        }
+        result.push_str(&format!(
+            "Phase {} of {}, total {} for white\n",
+            self.phase.min(MAX_PHASE),
+            MAX_PHASE,
+            self.total()
+        ));
+        return result;
+    }
+
+    fn add(&mut self, term: Term, side: usize, score: Score) {
+        self.scores[term as usize][side] += score;
+    }
+}
+
+/// all weights of the evaluation, the tuner changes them to fit the results of real games
+#[derive(Clone, Debug)]
+pub struct EvalParameters {
+    // indexed by the colorless piece like PIECE_VALUES, the king isn't counted
+    pub material: [Score; 7],
+    // written from white's point of view as the board is printed, a8 first and h1 last
+    pub piece_squares: [[Score; 64]; 7],
+    // indexed by the rank seen from the side of the pawn, a pawn on its starting rank is on rank 1
+    pub passed_pawns: [Score; 8],
+    pub connected_pawns: [Score; 8],
+    pub candidate_pawns: [Score; 8],
+    pub isolated_pawn: Score,
+    pub doubled_pawn: Score,
+    pub backward_pawn: Score,
+    // own pawns one and two rows in front of the king, on its file and the files next to it
+    pub pawn_shield_close: i32,
+    pub pawn_shield_far: i32,
+    // enemy pawns coming for the king, indexed by how many rows they are in front of it
+    pub pawn_storm: [i32; 5],
+    // files next to the king without own pawns, and the extra when there are no enemy pawns on it either
+    pub half_open_file_near_king: i32,
+    pub open_file_near_king: i32,
+    // how dangerous a piece attacking the squares around the king is, indexed by the colorless piece
+    pub king_attack_weights: [i32; 7],
+    // indexed by the number of squares the piece can move to that aren't attacked by enemy pawns
+    pub knight_mobility: [Score; 9],
+    pub bishop_mobility: [Score; 14],
+    pub rook_mobility: [Score; 15],
+    pub queen_mobility: [Score; 28],
+}
+
+impl Default for EvalParameters {
+    /// the weights the engine plays with
+    fn default() -> Self {
+        return DEFAULT_PARAMETERS.clone();
+    }
+}
+
+impl EvalParameters {
+    /// every weight the evaluation reads, in the order the fields are declared, the entries for no piece, the king's
+    /// material and the attack weights of pieces that aren't counted as attackers are left out
+    pub fn values_mut(&mut self) -> Vec<&mut i32> {
+        let mut values = Vec::new();
+        let scores = self.material[PAWN as usize..KING as usize]
+            .iter_mut()
+            .chain(self.piece_squares[PAWN as usize..].iter_mut().flatten())
+            .chain(self.passed_pawns.iter_mut())
+            .chain(self.connected_pawns.iter_mut())
+            .chain(self.candidate_pawns.iter_mut())
+            .chain([&mut self.isolated_pawn, &mut self.doubled_pawn, &mut self.backward_pawn])
+            .chain(self.knight_mobility.iter_mut())
+            .chain(self.bishop_mobility.iter_mut())
+            .chain(self.rook_mobility.iter_mut())
+            .chain(self.queen_mobility.iter_mut());
+        for score in scores {
+            values.push(&mut score.middlegame);
+            values.push(&mut score.endgame);
+        }
+        values.extend([&mut self.pawn_shield_close, &mut self.pawn_shield_far]);
+        values.extend(self.pawn_storm.iter_mut());
+        values.extend([&mut self.half_open_file_near_king, &mut self.open_file_near_king]);
+        values.extend(self.king_attack_weights[ROOK as usize..=QUEEN as usize].iter_mut());
+        return values;
+    }
+}
+
+/// static evaluation in centipawns from the perspective of the side to move
+pub fn evaluate(bs: &BoardState) -> i32 {
+    return side_to_move_score(bs, trace(bs).total());
+}
+
+/// the same as evaluate, but the pawn structure is looked up in the table first
+pub fn evaluate_with_pawn_table(bs: &BoardState, pawn_table: &mut PawnTable) -> i32 {
+    return side_to_move_score(bs, trace_with_pawns(bs, &pawn_table.probe(bs), &DEFAULT_PARAMETERS).total());
+}
+
+/// all terms of the evaluation, to see why the engine likes or dislikes a position
+pub fn trace(bs: &BoardState) -> EvalTrace {
+    return trace_with_parameters(bs, &DEFAULT_PARAMETERS);
+}
+
+pub fn trace_with_parameters(bs: &BoardState, parameters: &EvalParameters) -> EvalTrace {
+    return trace_with_pawns(bs, &evaluate_pawns(bs, parameters), parameters);
+}
+
+fn side_to_move_score(bs: &BoardState, white_score: i32) -> i32 {
+    return if bs.color_to_move() == WHITE { white_score } else { -white_score };
+}
+
+fn trace_with_pawns(bs: &BoardState, pawns: &PawnEntry, parameters: &EvalParameters) -> EvalTrace {
+    let mut trace = EvalTrace::default();
+    let mut kings = [0; 2];
+    for square in 0..64 {
+        let piece = bs.get_piece_at_position(Position::position_from_indices(square));
+        if piece == NOTHING {
+            continue;
+        }
+        let colorless_piece = (piece & PIECE_MASK) as usize;
+        let side = side_index(piece);
+        // the tables are written for white
+        let table_square = if side == 0 { square ^ 56 } else { square };
+        trace.add(Term::Material, side, parameters.material[colorless_piece]);
+        trace.add(Term::PieceSquares, side, parameters.piece_squares[colorless_piece][table_square]);
+        trace.phase += PHASE_WEIGHTS[colorless_piece];
+        if colorless_piece == KING as usize {
+            kings[side] = square;
+        }
+    }
+    for (side, king_square) in kings.iter().enumerate() {
+        trace.add(Term::PawnStructure, side, pawns.structure[side]);
+        trace.add(
+            Term::PassedPawns,
+            side,
+            pawns.passed[side] + blocked_passed_pawns(bs, pawns, side, parameters),
+        );
+        trace.add(Term::KingSafety, side, king_pawn_cover(pawns, side, *king_square, parameters));
+    }
+    evaluate_pieces(bs, pawns, &kings, parameters, &mut trace);
+    return trace;
+}
+
+/// mobility of the pieces and their attacks on the enemy king, both come from the squares the pieces reach
+fn evaluate_pieces(bs: &BoardState, pawns: &PawnEntry, kings: &[usize; 2], parameters: &EvalParameters, trace: &mut EvalTrace) {
+    let king_zones = [king_zone(kings[0]), king_zone(kings[1])];
+    let mut king_attackers = [0; 2];
+    let mut king_attack_weights = [0; 2];
+    for square in 0..64 {
+        let position = Position::position_from_indices(square);
+        let piece = bs.get_piece_at_position(position);
+        let colorless_piece = piece & PIECE_MASK;
+        if !matches!(colorless_piece, KNIGHT | BISHOP | ROOK | QUEEN) {
+            continue;
+        }
+        let side = side_index(piece);
+        let mut reachable = 0u64;
+        for target in bs.get_piece_moves_ignoring_checks(position) {
+            reachable |= 1 << Position::index_from_position(target);
+        }
+        let mobility = (reachable & !pawns.attacks[1 - side]).count_ones() as usize;
+        let mobility_score = match colorless_piece {
+            KNIGHT => parameters.knight_mobility[mobility],
+            BISHOP => parameters.bishop_mobility[mobility],
+            ROOK => parameters.rook_mobility[mobility],
+            _ => parameters.queen_mobility[mobility],
+        };
+        trace.add(Term::Mobility, side, mobility_score);
+        if reachable & king_zones[1 - side] != 0 {
+            king_attackers[1 - side] += 1;
+            king_attack_weights[1 - side] += parameters.king_attack_weights[colorless_piece as usize];
+        }
+    }
+    for side in 0..2 {
+        let danger = king_attack_weights[side] * KING_ATTACKER_SCALE[king_attackers[side].min(7)] / 100;
+        trace.add(Term::KingSafety, side, Score::new(-danger, -danger / 4));
+    }
+}
+
+/// pawn shield, pawn storm and open files on the file of the king and the files next to it
+fn king_pawn_cover(pawns: &PawnEntry, side: usize, king_square: usize, parameters: &EvalParameters) -> Score {
+    let own = pawns.pawns[side];
+    let enemy = pawns.pawns[1 - side];
+    let king_file = (king_square % 8) as i32;
+    let king_row = (king_square / 8) as i32;
+    let forward = if side == 0 { 1 } else { -1 };
+    let mut middlegame = 0;
+    for file in (king_file - 1).max(0)..=(king_file + 1).min(7) {
+        let file_mask = 0x0101_0101_0101_0101u64 << file;
+        for distance in 1..=4 {
+            let row = king_row + forward * distance;
+            if !(0..8).contains(&row) {
+                break;
+            }
+            let square = 1u64 << (row * 8 + file);
+            if own & square != 0 {
+                middlegame += match distance {
+                    1 => parameters.pawn_shield_close,
+                    2 => parameters.pawn_shield_far,
+                    _ => 0,
+                };
+            }
+            if enemy & square != 0 {
+                middlegame += parameters.pawn_storm[distance as usize];
+            }
+        }
+        if own & file_mask == 0 {
+            middlegame += parameters.half_open_file_near_king;
+            if enemy & file_mask == 0 {
+                middlegame += parameters.open_file_near_king;
+            }
+        }
+    }
+    // the cover matters while there are pieces to attack the king, the endgame king wants to be active instead
+    return Score::new(middlegame, 0);
+}
+
+/// the square of the king and all squares next to it
+fn king_zone(king_square: usize) -> u64 {
+    let mut zone = 0u64;
+    let king_row = (king_square / 8) as i32;
+    let king_file = (king_square % 8) as i32;
+    for row in (king_row - 1).max(0)..=(king_row + 1).min(7) {
+        for file in (king_file - 1).max(0)..=(king_file + 1).min(7) {
+            zone |= 1 << (row * 8 + file);
+        }
+    }
+    return zone;
+}
+
+fn side_index(piece_or_color: i32) -> usize {
+    return if piece_or_color & BLACK != 0 { 1 } else { 0 };
+}
@@ -0,0 +1,27 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+pub mod bench;
+pub mod board_state;
+pub mod book_builder;
+pub mod datagen;
+pub mod engine;
+mod eval_parameters;
+pub mod evaluation;
+pub mod move_ordering;
+pub mod nnue;
+pub mod pawn_structure;
+pub mod pgn;
+pub mod polyglot;
+mod polyglot_keys;
+pub mod problem;
+pub mod proof_search;
+pub mod search;
+pub mod selfplay;
+pub mod syzygy;
+pub mod tablebase;
+pub mod transposition_table;
+pub mod tuner;
+pub mod uci;
+pub mod xboard;
+mod zobrist;
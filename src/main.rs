@@ -1,9 +1,30 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::env;
 use std::io;
 use std::process::exit;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::board_state::{BoardState, Position};
-
-mod board_state;
+use chesstacean::bench;
+use chesstacean::board_state;
+use chesstacean::board_state::{BoardState, Move, Position};
+use chesstacean::book_builder::{self, BookOptions};
+use chesstacean::datagen;
+use chesstacean::engine::Engine;
+use chesstacean::evaluation;
+use chesstacean::polyglot::Book;
+use chesstacean::problem::{Solver, Stipulation};
+use chesstacean::proof_search::{self, Goal, ProofResult};
+use chesstacean::search::{SearchLimits, SearchOptions, SearchResult, Searcher};
+use chesstacean::selfplay::{self, GameResult};
+use chesstacean::syzygy::Tablebases;
+use chesstacean::tablebase::{EndgameTables, GeneratedTable, Generator};
+use chesstacean::tuner;
+use chesstacean::uci;
+use chesstacean::xboard;
 
 fn read_line(prompt: &str) -> String {
     let mut input = String::new();
@@ -17,7 +38,7 @@ fn read_line(prompt: &str) -> String {
 }
 
 fn game_loop() {
-    println!("Type \"exit\" at any point to stop the game");
+    println!("Type \"exit\" at any point to stop the game and \"eval\" to see how the engine judges the position");
     let mut bs: BoardState = BoardState::from_fen(board_state::CASTLING_TEST);
     loop {
         println!("{}", bs.full_state_str());
@@ -27,6 +48,10 @@ fn game_loop() {
         }
         let starting_position = loop {
             let line = read_line("Provide coordinates of piece:");
+            if line.to_lowercase() == "eval" {
+                print_evaluation(&bs);
+                continue;
+            }
             if line.len() != 2 {
                 continue;
             }
@@ -35,7 +60,7 @@ fn game_loop() {
 
         let original_square = board_state::Position::position_from_string(&starting_position);
         let moves = bs.get_piece_moves_respecting_checks(original_square);
-        if moves.len() == 0 {
+        if moves.is_empty() {
             println!("no possible moves");
             continue;
         }
@@ -54,10 +79,501 @@ fn game_loop() {
                 break position;
             }
         };
-        bs = bs.perform_move(original_square, new_position);
+        let move_string = format!("{}{}", original_square.str(), new_position.str());
+        // pawns reaching the last row become queens
+        let chess_move = bs
+            .move_from_string(&move_string)
+            .or_else(|| bs.move_from_string(&format!("{}q", move_string)))
+            .expect("selected move has to be legal");
+        let previous = bs;
+        bs = previous.make_move(chess_move);
+        warn_if_material_hangs(&previous, &bs);
+    }
+}
+
+/// checks whether the opponent can now win material with a capture it couldn't make before the move
+fn warn_if_material_hangs(previous: &BoardState, bs: &BoardState) {
+    // the pieces the opponent could already win if it were its turn don't count, unless the move was a check evasion
+    let already_hanging = if previous.is_in_check() {
+        Vec::new()
+    } else {
+        let passed = previous.make_null_move();
+        winning_captures(&passed)
+            .into_iter()
+            .map(|(chess_move, _)| (chess_move.to(), passed.get_piece_at_position(chess_move.to())))
+            .collect()
+    };
+    let best_capture = winning_captures(bs)
+        .into_iter()
+        .filter(|(chess_move, _)| !already_hanging.contains(&(chess_move.to(), bs.get_piece_at_position(chess_move.to()))))
+        .max_by_key(|(_, gain)| *gain);
+    if let Some((chess_move, gain)) = best_capture {
+        println!("Warning: this move hangs material, {} wins {} centipawns", chess_move.str(), gain);
+    }
+}
+
+/// the captures of the side to move that win material
+fn winning_captures(bs: &BoardState) -> Vec<(Move, i32)> {
+    return bs
+        .get_legal_moves()
+        .into_iter()
+        .filter(|chess_move| bs.is_capture(*chess_move))
+        .map(|chess_move| (chess_move, bs.see(chess_move)))
+        .filter(|(_, gain)| *gain > 0)
+        .collect();
+}
+
+/// the human plays one color and the engine the other, while the human thinks the engine already searches the
+/// position after the move it expects and keeps that search when the guess was right
+fn play(human_color: i32, move_time: Duration, book: Option<&str>) {
+    println!("Enter moves like \"e2e4\", \"eval\" shows how the engine judges the position and \"exit\" ends the game");
+    let mut engine = Engine::new();
+    if let Some(book) = book {
+        if let Err(error) = engine.set_option("BookFile", book) {
+            println!("{}", error);
+            return;
+        }
+    }
+    // the expected move of the human and the search of the position after it
+    let mut pondering: Option<(Move, Receiver<SearchResult>)> = None;
+    // a search of the current position that started while pondering
+    let mut search: Option<Receiver<SearchResult>> = None;
+    loop {
+        let bs = engine.position().clone();
+        println!("{}", bs.full_state_str());
+        if let Some(result) = engine.game_result() {
+            println!("Game over: {:?}", result);
+            engine.stop();
+            return;
+        }
+        if bs.color_to_move() == human_color {
+            let chess_move = loop {
+                let line = read_line("Your move:");
+                if line.to_lowercase() == "eval" {
+                    print_evaluation(&bs);
+                    continue;
+                }
+                // pawns reaching the last row become queens unless another piece is given
+                match bs.move_from_string(&line).or_else(|| bs.move_from_string(&format!("{}q", line))) {
+                    Some(chess_move) => break chess_move,
+                    None => println!("{} is not a legal move", line),
+                }
+            };
+            engine.make_move(chess_move);
+            search = match pondering.take() {
+                Some((ponder_move, receiver)) if ponder_move == chess_move => {
+                    // the search already runs on this position, from now on with the clock running
+                    engine.ponderhit();
+                    Some(receiver)
+                }
+                Some(_) => {
+                    // the wrong position, the search is of no use
+                    engine.stop();
+                    None
+                }
+                None => None,
+            };
+            continue;
+        }
+        let receiver = search.take().unwrap_or_else(|| start_search(&mut engine, SearchLimits::time(move_time)));
+        let result = receiver.recv().expect("every search sends its result");
+        engine.wait();
+        let best_move = result.best_move.expect("a position that isn't over has a legal move");
+        println!(
+            "chesstacean plays {} (depth {}, {})",
+            best_move.str(),
+            result.depth,
+            result.score_string()
+        );
+        engine.make_move(best_move);
+        if let Some(ponder_move) = result.ponder_move() {
+            engine.make_move(ponder_move);
+            let limits = SearchLimits {
+                ponder: true,
+                ..SearchLimits::time(move_time)
+            };
+            pondering = Some((ponder_move, start_search(&mut engine, limits)));
+            engine.undo();
+        }
+    }
+}
+
+/// a pondering search holds back its result until ponderhit, so the opponent's move decides about it
+fn start_search(engine: &mut Engine, limits: SearchLimits) -> Receiver<SearchResult> {
+    let (sender, receiver) = mpsc::channel();
+    let on_result = Box::new(move |result: SearchResult| {
+        let _ = sender.send(result);
+    });
+    engine.go(limits, limits.ponder, Box::new(|_: &SearchResult| {}), on_result);
+    return receiver;
+}
+
+/// the moves the book knows for the position and how often each of them would be played
+fn print_book_moves(path: &str, bs: &BoardState) {
+    let book = match Book::open(path) {
+        Ok(book) => book,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    println!("{} entries, key of the position {:016x}", book.len(), bs.polyglot_key());
+    let moves = book.moves(bs);
+    let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+    for (chess_move, weight) in &moves {
+        println!(
+            "{:<6}{:>8}{:>8.1}%",
+            chess_move.str(),
+            weight,
+            *weight as f64 * 100.0 / total.max(1) as f64
+        );
+    }
+    if moves.is_empty() {
+        println!("no book moves");
+    }
+}
+
+/// what the Syzygy tables say about the position and every move in it
+fn print_tablebase_probe(path: &str, bs: &BoardState) {
+    let tablebases = match Tablebases::open(path) {
+        Ok(tablebases) => tablebases,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    println!("{} tables with up to {} pieces", tablebases.table_count(), tablebases.max_pieces());
+    let (Some(wdl), Some(dtz)) = (tablebases.probe_wdl(bs), tablebases.probe_dtz(bs)) else {
+        println!("the position is not in the tables");
+        return;
+    };
+    println!("{:?}, DTZ {}", wdl, dtz);
+    for chess_move in bs.get_legal_moves() {
+        let new_state = bs.make_move(chess_move);
+        let wdl = tablebases
+            .probe_wdl(&new_state)
+            .map(|wdl| format!("{:?}", wdl.flipped()))
+            .unwrap_or(String::from("?"));
+        let dtz = tablebases
+            .probe_dtz(&new_state)
+            .map(|dtz| (-dtz).to_string())
+            .unwrap_or(String::from("?"));
+        println!("{:<6}{:<12}DTZ after the move {}", chess_move.str(), wdl, dtz);
+    }
+    if let Some(best_moves) = tablebases.root_moves(bs) {
+        println!(
+            "best moves: {}",
+            best_moves.iter().map(|chess_move| chess_move.str()).collect::<Vec<String>>().join(" ")
+        );
+    }
+}
+
+/// generates the ending and the smaller ones it needs and writes them to the directory
+fn generate_tablebases(name: &str, directory: &str) {
+    let mut generator = Generator::new();
+    generator.set_progress_callback(Box::new(|table: &GeneratedTable| {
+        println!(
+            "{}: {} positions, longest mate {} plies, {:.1}s",
+            table.name,
+            table.positions,
+            table.longest_mate,
+            table.time.as_secs_f64()
+        );
+    }));
+    if let Err(error) = generator.generate(name) {
+        println!("{}", error);
+        return;
+    }
+    for table in generator.tables() {
+        if let Err(error) = table.save(directory) {
+            println!("{}", error);
+            return;
+        }
     }
+    println!("{} tables written to {}", generator.tables().len(), directory);
+}
+
+/// the distance to mate of the position and after every move in it
+fn print_endgame_probe(path: &str, bs: &BoardState) {
+    let endgame_tables = match EndgameTables::load(path) {
+        Ok(endgame_tables) => endgame_tables,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let Some((best_moves, dtm)) = endgame_tables.best_moves(bs) else {
+        println!("the position is not in the tables");
+        return;
+    };
+    println!("{:?}", dtm);
+    for chess_move in bs.get_legal_moves() {
+        let dtm = endgame_tables
+            .probe(&bs.make_move(chess_move))
+            .map(|dtm| format!("{:?}", dtm.before_move()))
+            .unwrap_or(String::from("?"));
+        println!("{:<6}{}", chess_move.str(), dtm);
+    }
+    println!(
+        "best moves: {}",
+        best_moves.iter().map(|chess_move| chess_move.str()).collect::<Vec<String>>().join(" ")
+    );
+}
+
+/// prints every key with its solution tree and whether the problem is cooked
+fn solve_problem(bs: &BoardState, stipulation: Stipulation) {
+    let start = Instant::now();
+    let solution = Solver::new().solve(bs, stipulation);
+    if solution.keys.is_empty() {
+        println!("no solution for {}", stipulation.str());
+    } else {
+        println!("{}", solution.str(bs));
+    }
+    if solution.is_cooked() {
+        println!("cooked, {} solutions", solution.solution_count());
+    }
+    println!(
+        "{} positions searched in {:.2}s",
+        solution.positions_searched,
+        start.elapsed().as_secs_f64()
+    );
+}
+
+/// proves or disproves the goal for the side to move with proof-number search
+fn prove(bs: &BoardState, goal: Goal, max_plies: u32) {
+    let start = Instant::now();
+    let result = proof_search::prove(bs, goal, max_plies, proof_search::DEFAULT_MAX_NODES);
+    match result.result {
+        ProofResult::Proven => println!("proven, proof tree of {} positions", result.proof_tree_size),
+        ProofResult::Disproven => println!("disproven within {} plies", max_plies),
+        ProofResult::Unknown => println!("unknown, the node limit was reached"),
+    }
+    if !result.line.is_empty() {
+        println!(
+            "line: {}",
+            result.line.iter().map(|chess_move| chess_move.str()).collect::<Vec<String>>().join(" ")
+        );
+    }
+    println!("{} positions searched in {:.2}s", result.nodes, start.elapsed().as_secs_f64());
+}
+
+/// the output file, the PGN files and the options of the makebook command, None if they don't make sense
+fn parse_book_options(args: &[String]) -> Option<(String, Vec<String>, BookOptions)> {
+    let mut options = BookOptions::default();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plies" => options.max_plies = args.next()?.parse().ok()?,
+            "--min-games" => options.min_games = args.next()?.parse().ok()?,
+            "--results" => {
+                let mut results = Vec::new();
+                for result in args.next()?.split(',') {
+                    results.push(match result {
+                        "1-0" => GameResult::WhiteWins,
+                        "0-1" => GameResult::BlackWins,
+                        "1/2-1/2" => GameResult::Draw,
+                        _ => return None,
+                    });
+                }
+                options.results = results;
+            }
+            _ => paths.push(arg.clone()),
+        }
+    }
+    if paths.len() < 2 {
+        return None;
+    }
+    let output = paths.remove(0);
+    return Some((output, paths, options));
+}
+
+fn print_evaluation(bs: &BoardState) {
+    print!("{}", evaluation::trace(bs).str());
+    let side = if bs.color_to_move() == board_state::WHITE { "white" } else { "black" };
+    println!("Evaluation for the side to move ({}): {}", side, evaluation::evaluate(bs));
+}
+
+/// prints the best lines of a position for every finished depth
+fn analyze(fen: &str, depth: i32, lines: usize) {
+    let bs = BoardState::from_fen(fen);
+    let mut searcher = Searcher::new();
+    if let Err(error) = searcher.set_option("MultiPV", &lines.to_string()) {
+        println!("{}", error);
+        return;
+    }
+    searcher.set_iteration_callback(Box::new(|result: &SearchResult| {
+        println!("depth {} nodes {}", result.depth, result.nodes);
+        for (index, line) in result.lines.iter().enumerate() {
+            println!("  {:>2}. {:<10} {}", index + 1, line.score_string(), line.pv_string());
+        }
+    }));
+    searcher.search(&bs, &SearchLimits::depth(depth));
 }
 
 fn main() {
-    game_loop();
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("bench") if args.get(2).is_some_and(|arg| arg == "threads") => {
+            let depth = args
+                .get(3)
+                .and_then(|depth| depth.parse().ok())
+                .unwrap_or(bench::DEFAULT_THREAD_BENCH_DEPTH);
+            let max_threads = args
+                .get(4)
+                .and_then(|threads| threads.parse().ok())
+                .unwrap_or_else(|| thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1));
+            bench::run_thread_benchmark(depth, max_threads);
+        }
+        Some("bench") => {
+            let depth = args.get(2).and_then(|depth| depth.parse().ok()).unwrap_or(bench::DEFAULT_BENCH_DEPTH);
+            bench::run_node_benchmark(depth);
+        }
+        Some("selfplay") => {
+            // the default engine against one with the given search feature switched off
+            let feature = args.get(2).map(|arg| arg.as_str()).unwrap_or("");
+            let games = args.get(3).and_then(|games| games.parse().ok()).unwrap_or(20);
+            let nodes = args.get(4).and_then(|nodes| nodes.parse().ok()).unwrap_or(20000);
+            let mut opponent_options = SearchOptions::default();
+            match opponent_options.switch_mut(feature) {
+                Some(switch) => *switch = false,
+                None => {
+                    println!("usage: selfplay <NullMove|LateMoveReductions|ReverseFutilityPruning|FutilityPruning|Razoring|CheckExtensions|MoveOrdering> [games] [nodes]");
+                    return;
+                }
+            }
+            selfplay::run_match(SearchOptions::default(), opponent_options, games, &SearchLimits::nodes(nodes));
+        }
+        Some("analyze") => {
+            // analyze <depth> <lines> [fen], the starting position if no fen is given
+            let depth = args.get(2).and_then(|depth| depth.parse().ok()).unwrap_or(8);
+            let lines = args.get(3).and_then(|lines| lines.parse().ok()).unwrap_or(3);
+            let fen = if args.len() > 4 {
+                args[4..].join(" ")
+            } else {
+                board_state::STARTING_FEN.to_string()
+            };
+            analyze(&fen, depth, lines);
+        }
+        Some("eval") => {
+            let fen = if args.len() > 2 {
+                args[2..].join(" ")
+            } else {
+                board_state::STARTING_FEN.to_string()
+            };
+            let bs = BoardState::from_fen(&fen);
+            println!("{}", bs.str());
+            print_evaluation(&bs);
+        }
+        Some("tune") => {
+            let (Some(positions), Some(output)) = (args.get(2), args.get(3)) else {
+                println!("usage: tune <file with a FEN and the game result per line> <output file, e.g. src/eval_parameters.rs> [passes]");
+                return;
+            };
+            let passes = args.get(4).and_then(|passes| passes.parse().ok()).unwrap_or(tuner::DEFAULT_PASSES);
+            if let Err(error) = tuner::tune(positions, output, passes) {
+                println!("{}", error);
+            }
+        }
+        Some("datagen") => {
+            let Some(output) = args.get(2) else {
+                println!("usage: datagen <output file> [games] [nodes per move] [seed]");
+                return;
+            };
+            let games = args.get(3).and_then(|games| games.parse().ok()).unwrap_or(datagen::DEFAULT_GAMES);
+            let nodes = args.get(4).and_then(|nodes| nodes.parse().ok()).unwrap_or(datagen::DEFAULT_NODES);
+            let seed = args.get(5).and_then(|seed| seed.parse().ok()).unwrap_or(datagen::DEFAULT_SEED);
+            if let Err(error) = datagen::generate(output, games, nodes, seed) {
+                println!("{}", error);
+            }
+        }
+        Some("play") => {
+            // play [white|black] [seconds per move] [book], the human plays the given color
+            let human_color = if args.get(2).is_some_and(|arg| arg == "black") {
+                board_state::BLACK
+            } else {
+                board_state::WHITE
+            };
+            let seconds = args.get(3).and_then(|seconds| seconds.parse().ok()).unwrap_or(3.0);
+            play(human_color, Duration::from_secs_f64(seconds), args.get(4).map(|arg| arg.as_str()));
+        }
+        Some("book") => {
+            let Some(path) = args.get(2) else {
+                println!("usage: book <polyglot book> [fen]");
+                return;
+            };
+            let fen = if args.len() > 3 {
+                args[3..].join(" ")
+            } else {
+                board_state::STARTING_FEN.to_string()
+            };
+            print_book_moves(path, &BoardState::from_fen(&fen));
+        }
+        Some("makebook") => {
+            let Some((output, pgn_paths, options)) = parse_book_options(&args[2..]) else {
+                println!("usage: makebook <output book> <pgn files...> [--plies N] [--min-games N] [--results 1-0,0-1,1/2-1/2]");
+                return;
+            };
+            if let Err(error) = book_builder::build(&pgn_paths, &output, &options) {
+                println!("{}", error);
+            }
+        }
+        Some("syzygy") => {
+            let Some(path) = args.get(2) else {
+                println!("usage: syzygy <tablebase directory> [fen]");
+                return;
+            };
+            let fen = if args.len() > 3 {
+                args[3..].join(" ")
+            } else {
+                board_state::STARTING_FEN.to_string()
+            };
+            print_tablebase_probe(path, &BoardState::from_fen(&fen));
+        }
+        Some("tbgen") => {
+            let Some(name) = args.get(2) else {
+                println!("usage: tbgen <ending like KQvK> [output directory]");
+                return;
+            };
+            generate_tablebases(name, args.get(3).map(|directory| directory.as_str()).unwrap_or("."));
+        }
+        Some("tbprobe") => {
+            let Some(path) = args.get(2) else {
+                println!("usage: tbprobe <tablebase directory> <fen>");
+                return;
+            };
+            let fen = if args.len() > 3 {
+                args[3..].join(" ")
+            } else {
+                board_state::STARTING_FEN.to_string()
+            };
+            print_endgame_probe(path, &BoardState::from_fen(&fen));
+        }
+        Some("solve") => {
+            let (Some(stipulation), true) = (args.get(2).and_then(|text| Stipulation::parse(text)), args.len() > 3) else {
+                println!("usage: solve <stipulation like #2, h#2 or s#2> <fen>");
+                return;
+            };
+            solve_problem(&BoardState::from_fen(&args[3..].join(" ")), stipulation);
+        }
+        Some("prove") => {
+            let goal = args.get(2).and_then(|text| Goal::parse(text));
+            // the ply limit can be left out, the placement field of a FEN never parses as a number even when it starts
+            // with a digit like "8/8/..."
+            let max_plies = args.get(3).and_then(|text| text.parse().ok());
+            let fen_start = if max_plies.is_some() { 4 } else { 3 };
+            let (Some(goal), true) = (goal, args.len() > fen_start) else {
+                println!(
+                    "usage: prove <mate, material or material:centipawns> [max plies, {} by default] <fen>",
+                    proof_search::DEFAULT_MAX_PLIES
+                );
+                return;
+            };
+            let max_plies = max_plies.unwrap_or(proof_search::DEFAULT_MAX_PLIES);
+            prove(&BoardState::from_fen(&args[fen_start..].join(" ")), goal, max_plies);
+        }
+        Some("uci") => uci::run(),
+        Some("xboard") => xboard::run(),
+        _ => game_loop(),
+    }
 }
@@ -0,0 +1,273 @@
+use crate::board_state::{BoardState, Move, Position, NOTHING, PAWN, PIECE_MASK, PIECE_VALUES, QUEEN, WHITE};
+
+pub const MAX_PLY: usize = 128;
+const MAX_HISTORY: i32 = 16384;
+
+/// what the search learned about good quiet moves, shared by all nodes of one searcher
+pub struct OrderingTables {
+    // two quiet moves per ply that caused a beta cutoff
+    killers: Vec<[Option<Move>; 2]>,
+    // indexed by color, origin and target square
+    history: Vec<[[i32; 64]; 64]>,
+    // the move that refuted the previous move, indexed by the origin and target of the previous move
+    countermoves: Vec<[Option<Move>; 64]>,
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        return OrderingTables::new();
+    }
+}
+
+impl OrderingTables {
+    pub fn new() -> OrderingTables {
+        return OrderingTables {
+            killers: vec![[None; 2]; MAX_PLY],
+            history: vec![[[0; 64]; 64]; 2],
+            countermoves: vec![[None; 64]; 64],
+        };
+    }
+
+    pub fn clear(&mut self) {
+        *self = OrderingTables::new();
+    }
+
+    /// keeps the tables between searches, but old history matters less than the new one
+    pub fn age(&mut self) {
+        for killers in self.killers.iter_mut() {
+            *killers = [None; 2];
+        }
+        for color in self.history.iter_mut() {
+            for from in color.iter_mut() {
+                for score in from.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+    }
+
+    /// a quiet move caused a beta cutoff, the quiet moves searched before it did not
+    pub fn update_quiet_cutoff(&mut self, color: i32, ply: usize, depth: i32, cutoff_move: Move, previous_move: Option<Move>, tried_quiets: &[Move]) {
+        if ply < MAX_PLY && self.killers[ply][0] != Some(cutoff_move) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(cutoff_move);
+        }
+        let bonus = (depth * depth).min(400);
+        self.update_history(color, cutoff_move, bonus);
+        for quiet in tried_quiets {
+            if *quiet != cutoff_move {
+                self.update_history(color, *quiet, -bonus);
+            }
+        }
+        if let Some(previous_move) = previous_move {
+            self.countermoves[square(previous_move.from)][square(previous_move.to)] = Some(cutoff_move);
+        }
+    }
+
+    fn update_history(&mut self, color: i32, chess_move: Move, bonus: i32) {
+        let entry = &mut self.history[color_index(color)][square(chess_move.from)][square(chess_move.to)];
+        // the closer the score gets to the maximum the smaller the changes get
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    fn history_score(&self, color: i32, chess_move: Move) -> i32 {
+        return self.history[color_index(color)][square(chess_move.from)][square(chess_move.to)];
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stage {
+    Unordered,
+    HashMove,
+    Generate,
+    GoodCaptures,
+    Killers,
+    Countermove,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// hands out the moves of a position one at a time, most promising first, so a cutoff skips sorting the rest
+pub struct MovePicker {
+    stage: Stage,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    countermove: Option<Move>,
+    captures: Vec<(Move, i32)>,
+    bad_captures: Vec<(Move, i32)>,
+    quiets: Vec<(Move, i32)>,
+    captures_only: bool,
+}
+
+impl MovePicker {
+    pub fn new(tables: &OrderingTables, ply: usize, hash_move: Option<Move>, previous_move: Option<Move>) -> MovePicker {
+        let killers = if ply < MAX_PLY { tables.killers[ply] } else { [None; 2] };
+        let countermove = previous_move.and_then(|m| tables.countermoves[square(m.from)][square(m.to)]);
+        return MovePicker {
+            stage: Stage::HashMove,
+            hash_move,
+            killers,
+            countermove,
+            captures: Vec::new(),
+            bad_captures: Vec::new(),
+            quiets: Vec::new(),
+            captures_only: false,
+        };
+    }
+
+    /// for the quiescence search, only captures and queen promotions that don't lose material
+    pub fn new_captures(hash_move: Option<Move>) -> MovePicker {
+        return MovePicker {
+            stage: Stage::HashMove,
+            hash_move,
+            killers: [None; 2],
+            countermove: None,
+            captures: Vec::new(),
+            bad_captures: Vec::new(),
+            quiets: Vec::new(),
+            captures_only: true,
+        };
+    }
+
+    /// plays the moves in the order they are generated in, to measure what the ordering gains
+    pub fn new_unordered(bs: &BoardState) -> MovePicker {
+        let mut moves = bs.get_legal_moves();
+        moves.reverse();
+        return MovePicker {
+            stage: Stage::Unordered,
+            hash_move: None,
+            killers: [None; 2],
+            countermove: None,
+            captures: Vec::new(),
+            bad_captures: Vec::new(),
+            quiets: moves.into_iter().map(|m| (m, 0)).collect(),
+            captures_only: false,
+        };
+    }
+
+    pub fn next(&mut self, bs: &BoardState, tables: &OrderingTables) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::Unordered => return self.quiets.pop().map(|(m, _)| m),
+                Stage::HashMove => {
+                    self.stage = Stage::Generate;
+                    if let Some(hash_move) = self.hash_move {
+                        if (!self.captures_only || is_tactical(bs, hash_move)) && bs.is_legal_move(hash_move) {
+                            return Some(hash_move);
+                        }
+                        self.hash_move = None;
+                    }
+                }
+                Stage::Generate => {
+                    self.generate(bs, tables);
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    if let Some(chess_move) = pick_best(&mut self.captures) {
+                        return Some(chess_move);
+                    }
+                    // captures that lose material are not worth looking at in the quiescence search
+                    self.stage = if self.captures_only { Stage::Done } else { Stage::Killers };
+                }
+                Stage::Killers => {
+                    // killers come from sibling positions, they are only played if they are a legal quiet move here
+                    for i in 0..2 {
+                        if let Some(killer) = self.killers[i].take() {
+                            if self.take_quiet(killer) {
+                                return Some(killer);
+                            }
+                        }
+                    }
+                    self.stage = Stage::Countermove;
+                }
+                Stage::Countermove => {
+                    self.stage = Stage::Quiets;
+                    if let Some(countermove) = self.countermove {
+                        if self.take_quiet(countermove) {
+                            return Some(countermove);
+                        }
+                    }
+                }
+                Stage::Quiets => {
+                    if let Some(chess_move) = pick_best(&mut self.quiets) {
+                        return Some(chess_move);
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if let Some(chess_move) = pick_best(&mut self.bad_captures) {
+                        return Some(chess_move);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// removes the move from the quiet moves still to be played, returns false if it isn't one of them
+    fn take_quiet(&mut self, chess_move: Move) -> bool {
+        if let Some(index) = self.quiets.iter().position(|(m, _)| *m == chess_move) {
+            self.quiets.swap_remove(index);
+            return true;
+        }
+        return false;
+    }
+
+    fn generate(&mut self, bs: &BoardState, tables: &OrderingTables) {
+        for chess_move in bs.get_legal_moves() {
+            if Some(chess_move) == self.hash_move {
+                continue;
+            }
+            if is_tactical(bs, chess_move) {
+                let attacker = bs.get_piece_at_position(chess_move.from) & PIECE_MASK;
+                let victim = captured_piece(bs, chess_move);
+                let score = PIECE_VALUES[victim as usize] * 10 - PIECE_VALUES[attacker as usize] / 10 + PIECE_VALUES[chess_move.promotion as usize];
+                // winning or even trades come first, losing ones only after all quiet moves
+                if bs.see(chess_move) >= 0 {
+                    self.captures.push((chess_move, score));
+                } else {
+                    self.bad_captures.push((chess_move, score));
+                }
+            } else if !self.captures_only {
+                self.quiets.push((chess_move, tables.history_score(bs.color_to_move(), chess_move)));
+            }
+        }
+    }
+}
+
+/// captures and queen promotions, the moves the quiescence search looks at
+pub fn is_tactical(bs: &BoardState, chess_move: Move) -> bool {
+    return bs.is_capture(chess_move) || chess_move.promotion == QUEEN;
+}
+
+fn captured_piece(bs: &BoardState, chess_move: Move) -> i32 {
+    let piece = bs.get_piece_at_position(chess_move.to) & PIECE_MASK;
+    if piece == NOTHING && bs.is_capture(chess_move) {
+        // en-passant
+        return PAWN;
+    }
+    return piece;
+}
+
+/// removes and returns the highest scored move, a selection sort step is cheaper than sorting when a cutoff follows
+fn pick_best(moves: &mut Vec<(Move, i32)>) -> Option<Move> {
+    let mut best_index = None;
+    let mut best_score = i32::MIN;
+    for (index, (_, score)) in moves.iter().enumerate() {
+        if *score > best_score {
+            best_score = *score;
+            best_index = Some(index);
+        }
+    }
+    return best_index.map(|index| moves.swap_remove(index).0);
+}
+
+fn square(position: Position) -> usize {
+    return Position::index_from_position(position);
+}
+
+fn color_index(color: i32) -> usize {
+    return if color == WHITE { 0 } else { 1 };
+}
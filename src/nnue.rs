@@ -0,0 +1,284 @@
+use std::fs;
+
+use crate::board_state::{BoardState, Move, Position, BISHOP, BLACK, KING, KNIGHT, NOTHING, PAWN, PIECE_MASK, QUEEN, ROOK, WHITE};
+use crate::search::MATE_THRESHOLD;
+
+// the file starts with the magic bytes, then the version, the feature set, the accumulator size and the hidden layer size
+// as little endian u32, followed by the feature weights (i16, input major), feature biases (i16), hidden weights
+// (i8, one row of both accumulators per neuron), hidden biases (i32), output weights (i8) and the output bias (i32)
+const MAGIC: &[u8; 4] = b"CTNN";
+const VERSION: u32 = 1;
+const MAX_ACCUMULATOR_SIZE: usize = 2048;
+const MAX_HIDDEN_SIZE: usize = 256;
+
+// activations are clipped to 0..=127 which stands for 0.0..=1.0, weights of the dense layers are scaled by 64
+const ACTIVATION_MAX: i32 = 127;
+const WEIGHT_SCALE: i32 = 64;
+// an output of 1.0 is worth this many centipawns
+const OUTPUT_SCALE: i32 = 400;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeatureSet {
+    /// the king square of the perspective with every piece but the kings on every square
+    HalfKP,
+    /// the same, but the kings are features too
+    HalfKA,
+}
+
+impl FeatureSet {
+    fn piece_count(&self) -> usize {
+        return match self {
+            FeatureSet::HalfKP => 10,
+            FeatureSet::HalfKA => 12,
+        };
+    }
+
+    fn input_count(&self) -> usize {
+        return 64 * self.piece_count() * 64;
+    }
+}
+
+/// the sum of the feature weights of all active features, one for white's and one for black's perspective, the sums
+/// wrap around like the i16 arithmetic of other engines so any network can be loaded
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+    values: [Vec<i16>; 2],
+}
+
+/// a quantised network with a feature transformer into the accumulators and two small dense layers on top
+pub struct Network {
+    feature_set: FeatureSet,
+    accumulator_size: usize,
+    hidden_size: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    hidden_weights: Vec<i8>,
+    hidden_biases: Vec<i32>,
+    output_weights: Vec<i8>,
+    output_bias: i32,
+}
+
+impl Network {
+    pub fn load(path: &str) -> Result<Network, String> {
+        let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        return Network::from_bytes(&bytes);
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, String> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(String::from("Not a network file"));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("Unsupported network version {}", version));
+        }
+        let feature_set = match reader.u32()? {
+            0 => FeatureSet::HalfKP,
+            1 => FeatureSet::HalfKA,
+            other => return Err(format!("Unknown feature set {}", other)),
+        };
+        let accumulator_size = reader.u32()? as usize;
+        let hidden_size = reader.u32()? as usize;
+        if accumulator_size == 0 || accumulator_size > MAX_ACCUMULATOR_SIZE || hidden_size == 0 || hidden_size > MAX_HIDDEN_SIZE {
+            return Err(format!("Unsupported layer sizes {} and {}", accumulator_size, hidden_size));
+        }
+        let network = Network {
+            feature_set,
+            accumulator_size,
+            hidden_size,
+            feature_weights: reader.i16s(feature_set.input_count() * accumulator_size)?,
+            feature_biases: reader.i16s(accumulator_size)?,
+            hidden_weights: reader.i8s(2 * accumulator_size * hidden_size)?,
+            hidden_biases: reader.i32s(hidden_size)?,
+            output_weights: reader.i8s(hidden_size)?,
+            output_bias: reader.i32()?,
+        };
+        if reader.offset != bytes.len() {
+            return Err(String::from("The network file is longer than its layers"));
+        }
+        return Ok(network);
+    }
+
+    pub fn new_accumulator(&self) -> Accumulator {
+        return Accumulator {
+            values: [vec![0; self.accumulator_size], vec![0; self.accumulator_size]],
+        };
+    }
+
+    /// computes both perspectives from scratch
+    pub fn refresh(&self, bs: &BoardState, accumulator: &mut Accumulator) {
+        let kings = king_squares(bs);
+        for (perspective, values) in accumulator.values.iter_mut().enumerate() {
+            self.refresh_perspective(bs, perspective, kings[perspective], values);
+        }
+    }
+
+    /// the accumulator of the position after a move from the one before it, only the squares of the move are updated,
+    /// a perspective whose king moved starts over because all of its features depend on the king square, without a
+    /// move the turn was passed and nothing changes
+    pub fn update(&self, before: &BoardState, chess_move: Option<Move>, after: &BoardState, parent: &Accumulator, child: &mut Accumulator) {
+        let squares = match chess_move {
+            Some(chess_move) => changed_squares(before, chess_move),
+            None => Vec::new(),
+        };
+        let kings_before = king_squares(before);
+        let kings_after = king_squares(after);
+        for perspective in 0..2 {
+            if kings_before[perspective] != kings_after[perspective] {
+                self.refresh_perspective(after, perspective, kings_after[perspective], &mut child.values[perspective]);
+                continue;
+            }
+            let values = &mut child.values[perspective];
+            values.copy_from_slice(&parent.values[perspective]);
+            for square in &squares {
+                let square = *square;
+                let position = Position::position_from_indices(square);
+                let old_piece = before.get_piece_at_position(position);
+                let new_piece = after.get_piece_at_position(position);
+                if old_piece == new_piece {
+                    continue;
+                }
+                if let Some(feature) = self.feature_index(perspective, kings_after[perspective], old_piece, square) {
+                    for (value, weight) in values.iter_mut().zip(self.feature_weights(feature)) {
+                        *value = value.wrapping_sub(*weight);
+                    }
+                }
+                if let Some(feature) = self.feature_index(perspective, kings_after[perspective], new_piece, square) {
+                    for (value, weight) in values.iter_mut().zip(self.feature_weights(feature)) {
+                        *value = value.wrapping_add(*weight);
+                    }
+                }
+            }
+        }
+    }
+
+    /// centipawns from the perspective of the side to move
+    pub fn evaluate(&self, accumulator: &Accumulator, color_to_move: i32) -> i32 {
+        let (us, them) = if color_to_move == WHITE { (0, 1) } else { (1, 0) };
+        // the sums are wide enough for any weights the file may hold, the result stays below the mate scores
+        let mut hidden = [0; MAX_HIDDEN_SIZE];
+        for (neuron, activation) in hidden.iter_mut().take(self.hidden_size).enumerate() {
+            let weights = &self.hidden_weights[neuron * 2 * self.accumulator_size..(neuron + 1) * 2 * self.accumulator_size];
+            let mut sum = self.hidden_biases[neuron] as i64;
+            let inputs = accumulator.values[us].iter().chain(accumulator.values[them].iter());
+            for (input, weight) in inputs.zip(weights) {
+                sum += (*input as i64).clamp(0, ACTIVATION_MAX as i64) * *weight as i64;
+            }
+            *activation = (sum / WEIGHT_SCALE as i64).clamp(0, ACTIVATION_MAX as i64);
+        }
+        let mut output = self.output_bias as i64;
+        for (activation, weight) in hidden.iter().zip(&self.output_weights) {
+            output += activation * *weight as i64;
+        }
+        let centipawns = output * OUTPUT_SCALE as i64 / (ACTIVATION_MAX * WEIGHT_SCALE) as i64;
+        return centipawns.clamp(-(MATE_THRESHOLD as i64 - 1), MATE_THRESHOLD as i64 - 1) as i32;
+    }
+
+    fn refresh_perspective(&self, bs: &BoardState, perspective: usize, king_square: usize, values: &mut [i16]) {
+        values.copy_from_slice(&self.feature_biases);
+        for square in 0..64 {
+            let piece = bs.get_piece_at_position(Position::position_from_indices(square));
+            if let Some(feature) = self.feature_index(perspective, king_square, piece, square) {
+                for (value, weight) in values.iter_mut().zip(self.feature_weights(feature)) {
+                    *value = value.wrapping_add(*weight);
+                }
+            }
+        }
+    }
+
+    fn feature_weights(&self, feature: usize) -> &[i16] {
+        return &self.feature_weights[feature * self.accumulator_size..(feature + 1) * self.accumulator_size];
+    }
+
+    /// black's perspective sees the board flipped, so both sides look at the position the same way
+    fn feature_index(&self, perspective: usize, king_square: usize, piece: i32, square: usize) -> Option<usize> {
+        let piece_type = match piece & PIECE_MASK {
+            PAWN => 0,
+            KNIGHT => 1,
+            BISHOP => 2,
+            ROOK => 3,
+            QUEEN => 4,
+            KING if self.feature_set == FeatureSet::HalfKA => 5,
+            _ => return None,
+        };
+        let is_enemy = (piece & BLACK != 0) != (perspective == 1);
+        let piece_index = piece_type * 2 + is_enemy as usize;
+        let orient = |square: usize| if perspective == 0 { square } else { square ^ 56 };
+        return Some((orient(king_square) * self.feature_set.piece_count() + piece_index) * 64 + orient(square));
+    }
+}
+
+/// the squares whose piece the move changes: where it starts and ends, the pawn taken en passant and the rook of
+/// a castling move
+fn changed_squares(before: &BoardState, chess_move: Move) -> Vec<usize> {
+    let (from, to) = (chess_move.from, chess_move.to);
+    let mut squares = vec![Position::index_from_position(from), Position::index_from_position(to)];
+    let piece = before.get_piece_at_position(from) & PIECE_MASK;
+    if piece == PAWN && from.col != to.col && before.get_piece_at_position(to) == NOTHING {
+        squares.push(Position::index_from_position(Position { row: from.row, col: to.col }));
+    }
+    if piece == KING && (to.col - from.col).abs() == 2 {
+        let (corner_col, rook_col) = if to.col > from.col { (7, 5) } else { (0, 3) };
+        squares.push(Position::index_from_position(Position {
+            row: from.row,
+            col: corner_col,
+        }));
+        squares.push(Position::index_from_position(Position {
+            row: from.row,
+            col: rook_col,
+        }));
+    }
+    return squares;
+}
+
+fn king_squares(bs: &BoardState) -> [usize; 2] {
+    let mut kings = [0; 2];
+    for square in 0..64 {
+        let piece = bs.get_piece_at_position(Position::position_from_indices(square));
+        if piece == KING | WHITE {
+            kings[0] = square;
+        } else if piece == KING | BLACK {
+            kings[1] = square;
+        }
+    }
+    return kings;
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        if self.offset + count > self.bytes.len() {
+            return Err(String::from("The network file ends too early"));
+        }
+        let slice = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        return Ok(slice);
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        return Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        return Ok(self.u32()? as i32);
+    }
+
+    fn i32s(&mut self, count: usize) -> Result<Vec<i32>, String> {
+        return (0..count).map(|_| self.i32()).collect();
+    }
+
+    fn i16s(&mut self, count: usize) -> Result<Vec<i16>, String> {
+        let bytes = self.take(count * 2)?;
+        return Ok(bytes.chunks_exact(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect());
+    }
+
+    fn i8s(&mut self, count: usize) -> Result<Vec<i8>, String> {
+        return Ok(self.take(count)?.iter().map(|byte| *byte as i8).collect());
+    }
+}
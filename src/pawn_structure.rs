@@ -0,0 +1,187 @@
+use crate::board_state::{BoardState, Position, BLACK, PAWN, WHITE};
+use crate::eval_parameters::DEFAULT_PARAMETERS;
+use crate::evaluation::{EvalParameters, Score};
+
+const DEFAULT_PAWN_TABLE_ENTRIES: usize = 16384;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const RANK_1: u64 = 0xFF;
+
+/// the pawn structure of both sides, white first and each from its own point of view,
+/// everything here depends on the pawns only so it can be cached by the pawn key
+#[derive(Clone, Copy, Default)]
+pub struct PawnEntry {
+    key: u64,
+    pub structure: [Score; 2],
+    pub passed: [Score; 2],
+    // one bit per square like the board indices
+    pub pawns: [u64; 2],
+    pub passed_pawns: [u64; 2],
+    pub attacks: [u64; 2],
+}
+
+/// caches the pawn structure, the same pawns come up again and again during a search
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+    hits: u64,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        return PawnTable::new();
+    }
+}
+
+impl PawnTable {
+    pub fn new() -> PawnTable {
+        return PawnTable {
+            entries: vec![PawnEntry::default(); DEFAULT_PAWN_TABLE_ENTRIES],
+            hits: 0,
+        };
+    }
+
+    /// an empty entry has key 0 which is also the key of a position without pawns, its empty score is the right one
+    pub fn probe(&mut self, bs: &BoardState) -> PawnEntry {
+        let key = bs.pawn_key();
+        let index = (key % self.entries.len() as u64) as usize;
+        if self.entries[index].key == key {
+            self.hits += 1;
+        } else {
+            self.entries[index] = evaluate_pawns(bs, &DEFAULT_PARAMETERS);
+        }
+        return self.entries[index];
+    }
+
+    /// how often a probe found the pawn structure already evaluated
+    pub fn hits(&self) -> u64 {
+        return self.hits;
+    }
+}
+
+pub fn evaluate_pawns(bs: &BoardState, parameters: &EvalParameters) -> PawnEntry {
+    let pawns = [pawn_bitboard(bs, WHITE), pawn_bitboard(bs, BLACK)];
+    let mut entry = PawnEntry {
+        key: bs.pawn_key(),
+        pawns,
+        attacks: [pawn_attacks(pawns[0], WHITE), pawn_attacks(pawns[1], BLACK)],
+        ..Default::default()
+    };
+    for (side, color) in [WHITE, BLACK].into_iter().enumerate() {
+        let own = pawns[side];
+        let enemy = pawns[1 - side];
+        let mut remaining = own;
+        while remaining != 0 {
+            let square = remaining.trailing_zeros() as usize;
+            remaining &= remaining - 1;
+            let file = square % 8;
+            let row = square / 8;
+            let relative_row = if color == WHITE { row } else { 7 - row };
+            let ahead = ranks_ahead(color, row);
+            let file_mask = FILE_A << file;
+            let adjacent = adjacent_files(file);
+            let behind_row = if color == WHITE { row as i32 - 1 } else { row as i32 + 1 };
+
+            let passed = enemy & (file_mask | adjacent) & ahead == 0;
+            let doubled = own & file_mask & ahead != 0;
+            let isolated = own & adjacent == 0;
+            let supported = own & adjacent & rank_mask(behind_row) != 0;
+            let phalanx = own & adjacent & rank_mask(row as i32) != 0;
+            // no pawn next to or behind it can ever protect it and an enemy pawn controls the square in front of it
+            let enemy_attack_row = if color == WHITE { row as i32 + 2 } else { row as i32 - 2 };
+            let stop_attacked = enemy & adjacent & rank_mask(enemy_attack_row) != 0;
+            let backward = !isolated && own & adjacent & !ahead == 0 && stop_attacked;
+
+            let mut score = Score::default();
+            // only the front pawn of doubled pawns can be passed or a candidate
+            if passed && !doubled {
+                entry.passed[side] += parameters.passed_pawns[relative_row];
+                entry.passed_pawns[side] |= 1 << square;
+            } else if !doubled && enemy & file_mask & ahead == 0 {
+                // an open file and at least as many own pawns to help it through as enemy pawns stopping it
+                let helpers = (own & adjacent & !ahead).count_ones();
+                let sentries = (enemy & adjacent & ahead).count_ones();
+                if helpers >= sentries {
+                    score += parameters.candidate_pawns[relative_row];
+                }
+            }
+            if isolated {
+                score += parameters.isolated_pawn;
+            } else if backward {
+                score += parameters.backward_pawn;
+            }
+            if doubled {
+                score += parameters.doubled_pawn;
+            }
+            if supported || phalanx {
+                score += parameters.connected_pawns[relative_row];
+            }
+            entry.structure[side] += score;
+        }
+    }
+    return entry;
+}
+
+/// a passed pawn with a piece in front of it can't run, it keeps only half its bonus
+pub fn blocked_passed_pawns(bs: &BoardState, entry: &PawnEntry, side: usize, parameters: &EvalParameters) -> Score {
+    let mut score = Score::default();
+    let mut remaining = entry.passed_pawns[side];
+    while remaining != 0 {
+        let square = remaining.trailing_zeros() as usize;
+        remaining &= remaining - 1;
+        // a pawn from a FEN can stand on the last row already
+        let stop_square = if side == 0 { square + 8 } else { square.wrapping_sub(8) };
+        if stop_square < 64 && bs.get_piece_at_position(Position::position_from_indices(stop_square)) != 0 {
+            let relative_row = if side == 0 { square / 8 } else { 7 - square / 8 };
+            let bonus = parameters.passed_pawns[relative_row];
+            score -= Score::new(bonus.middlegame / 2, bonus.endgame / 2);
+        }
+    }
+    return score;
+}
+
+fn pawn_bitboard(bs: &BoardState, color: i32) -> u64 {
+    let mut bitboard = 0;
+    for square in 0..64 {
+        if bs.get_piece_at_position(Position::position_from_indices(square)) == PAWN | color {
+            bitboard |= 1 << square;
+        }
+    }
+    return bitboard;
+}
+
+/// the squares the pawns attack, pawns on the a and h file only attack towards the center
+fn pawn_attacks(pawns: u64, color: i32) -> u64 {
+    let not_file_a = !FILE_A;
+    let not_file_h = !(FILE_A << 7);
+    if color == WHITE {
+        return (pawns & not_file_a) << 7 | (pawns & not_file_h) << 9;
+    }
+    return (pawns & not_file_a) >> 9 | (pawns & not_file_h) >> 7;
+}
+
+/// all squares on the rows in front of the row, seen from the given color
+fn ranks_ahead(color: i32, row: usize) -> u64 {
+    if color == WHITE {
+        return if row >= 7 { 0 } else { !0 << (8 * (row + 1)) };
+    }
+    return (1 << (8 * row)) - 1;
+}
+
+/// the squares of the row, none if the row is off the board
+fn rank_mask(row: i32) -> u64 {
+    if !(0..8).contains(&row) {
+        return 0;
+    }
+    return RANK_1 << (8 * row);
+}
+
+fn adjacent_files(file: usize) -> u64 {
+    let mut mask = 0;
+    if file > 0 {
+        mask |= FILE_A << (file - 1);
+    }
+    if file < 7 {
+        mask |= FILE_A << (file + 1);
+    }
+    return mask;
+}
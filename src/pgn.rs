@@ -0,0 +1,149 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::board_state::{BoardState, Move};
+use crate::selfplay::GameResult;
+
+/// a game read from a PGN file, only the main line, variations and comments are left out
+pub struct PgnGame {
+    pub start: BoardState,
+    pub moves: Vec<Move>,
+    /// from the Result tag or the end of the move text, None for unfinished games
+    pub result: Option<GameResult>,
+    /// the first move that couldn't be read, the moves before it are kept
+    pub error: Option<String>,
+}
+
+impl PgnGame {
+    fn new() -> PgnGame {
+        return PgnGame {
+            start: BoardState::new(),
+            moves: Vec::new(),
+            result: None,
+            error: None,
+        };
+    }
+}
+
+/// every game of a PGN text, a game ends with its result or when the tags of the next one begin
+pub fn parse_games(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::new();
+    let mut bs = BoardState::new();
+    let mut in_move_text = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                if in_move_text {
+                    games.push(game);
+                    game = PgnGame::new();
+                    bs = BoardState::new();
+                    in_move_text = false;
+                }
+                let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                if let Some(start) = read_tag(&tag, &mut game) {
+                    bs = start;
+                }
+            }
+            '{' => {
+                chars.by_ref().find(|c| *c == '}');
+            }
+            ';' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '(' => skip_variation(&mut chars),
+            _ if c.is_whitespace() => {}
+            _ => {
+                in_move_text = true;
+                let token = read_token(c, &mut chars);
+                if let Some(result) = parse_result(&token) {
+                    if game.result.is_none() {
+                        game.result = result;
+                    }
+                    games.push(game);
+                    game = PgnGame::new();
+                    bs = BoardState::new();
+                    in_move_text = false;
+                    continue;
+                }
+                // move numbers like "12." or "12..." and annotations like "$1" aren't moves
+                let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if san.is_empty() || san.starts_with('$') || game.error.is_some() {
+                    continue;
+                }
+                match bs.move_from_san(san) {
+                    Some(chess_move) => {
+                        game.moves.push(chess_move);
+                        bs = bs.make_move(chess_move);
+                    }
+                    None => game.error = Some(format!("Illegal or ambiguous move {} after {} moves", san, game.moves.len())),
+                }
+            }
+        }
+    }
+    if in_move_text {
+        games.push(game);
+    }
+    return games;
+}
+
+/// the tags that matter here are the result and the starting position, which is returned
+fn read_tag(tag: &str, game: &mut PgnGame) -> Option<BoardState> {
+    let (name, value) = tag.split_once(' ')?;
+    let value = value.trim().trim_matches('"');
+    match name {
+        "Result" => game.result = parse_result(value).flatten(),
+        "FEN" => {
+            let fields: Vec<&str> = value.split_whitespace().collect();
+            if fields.len() == 6 {
+                game.start = BoardState::from_fen(value);
+                return Some(game.start.clone());
+            }
+            game.error = Some(format!("Unsupported FEN {}", value));
+        }
+        _ => {}
+    }
+    return None;
+}
+
+/// Some(None) for "*", the result of a game that isn't over
+fn parse_result(token: &str) -> Option<Option<GameResult>> {
+    return match token {
+        "1-0" => Some(Some(GameResult::WhiteWins)),
+        "0-1" => Some(Some(GameResult::BlackWins)),
+        "1/2-1/2" => Some(Some(GameResult::Draw)),
+        "*" => Some(None),
+        _ => None,
+    };
+}
+
+fn read_token(first: char, chars: &mut Peekable<Chars>) -> String {
+    let mut token = String::from(first);
+    while let Some(c) = chars.peek() {
+        if c.is_whitespace() || "[]{}();".contains(*c) {
+            break;
+        }
+        token.push(*c);
+        chars.next();
+    }
+    return token;
+}
+
+/// variations can contain comments and further variations
+fn skip_variation(chars: &mut Peekable<Chars>) {
+    let mut depth = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '{' => {
+                chars.by_ref().find(|c| *c == '}');
+            }
+            _ => {}
+        }
+        if depth == 0 {
+            return;
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::board_state::{BoardState, Move, Position, BISHOP, KING, KNIGHT, PIECE_MASK, QUEEN, ROOK};
+
+// every entry is 16 big endian bytes: the key, the move, its weight and four bytes of learning data
+const ENTRY_SIZE: usize = 16;
+const PROMOTION_CHARS: [&str; 5] = ["", "n", "b", "r", "q"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    /// the move as Polyglot encodes it, see decode_move
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BookSelection {
+    /// always the move with the highest weight
+    Best,
+    /// any book move, the higher its weight the more likely
+    WeightedRandom,
+}
+
+/// a Polyglot opening book, the entries are sorted by key so all moves of a position are next to each other
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn open(path: &str) -> Result<Book, String> {
+        let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        return Book::from_bytes(&bytes);
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Book, String> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(String::from("Not a Polyglot book, the size isn't a multiple of 16 bytes"));
+        }
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().expect("eight bytes")),
+                raw_move: u16::from_be_bytes([chunk[8], chunk[9]]),
+                weight: u16::from_be_bytes([chunk[10], chunk[11]]),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().expect("four bytes")),
+            })
+            .collect();
+        // books should be sorted already, a stable sort keeps the order of the moves of a position
+        entries.sort_by_key(|entry| entry.key);
+        return Ok(Book { entries });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /// the legal book moves of the position with their weights, the highest weight first
+    pub fn moves(&self, bs: &BoardState) -> Vec<(Move, u16)> {
+        let key = bs.polyglot_key();
+        let first = self.entries.partition_point(|entry| entry.key < key);
+        let mut moves: Vec<(Move, u16)> = self.entries[first..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| decode_move(bs, entry.raw_move).map(|chess_move| (chess_move, entry.weight)))
+            .collect();
+        moves.sort_by_key(|(_, weight)| u16::MAX - weight);
+        return moves;
+    }
+
+    /// a move to play from the book, moves with weight 0 are only in the book to be avoided
+    pub fn choose_move(&self, bs: &BoardState, selection: BookSelection) -> Option<Move> {
+        let moves: Vec<(Move, u16)> = self.moves(bs).into_iter().filter(|(_, weight)| *weight > 0).collect();
+        if selection == BookSelection::Best || moves.len() <= 1 {
+            return moves.first().map(|(chess_move, _)| *chess_move);
+        }
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+        let mut pick = RandomState::new().build_hasher().finish() % total;
+        for (chess_move, weight) in &moves {
+            if pick < *weight as u64 {
+                return Some(*chess_move);
+            }
+            pick -= *weight as u64;
+        }
+        return None;
+    }
+}
+
+/// writes the entries sorted the way Polyglot expects, by key and the highest weight first
+pub fn write_book(path: &str, entries: &[BookEntry]) -> Result<(), String> {
+    let mut entries = entries.to_vec();
+    entries.sort_by_key(|entry| (entry.key, u16::MAX - entry.weight));
+    let mut bytes = Vec::with_capacity(entries.len() * ENTRY_SIZE);
+    for entry in &entries {
+        bytes.extend(entry.key.to_be_bytes());
+        bytes.extend(entry.raw_move.to_be_bytes());
+        bytes.extend(entry.weight.to_be_bytes());
+        bytes.extend(entry.learn.to_be_bytes());
+    }
+    return fs::write(path, bytes).map_err(|error| format!("Could not write {}: {}", path, error));
+}
+
+/// three bits each for the file and row of the target square, the file and row of the start square and the
+/// promotion piece (none, knight, bishop, rook, queen), castling is written as the king taking its own rook
+pub fn decode_move(bs: &BoardState, raw_move: u16) -> Option<Move> {
+    let from = Position::position_from_indices(((raw_move >> 6) & 63) as usize);
+    let mut to = Position::position_from_indices((raw_move & 63) as usize);
+    let promotion = PROMOTION_CHARS.get(((raw_move >> 12) & 7) as usize)?;
+    if bs.get_piece_at_position(from) & PIECE_MASK == KING && from.col == 4 && (to.col == 0 || to.col == 7) && from.row == to.row {
+        to = Position {
+            row: to.row,
+            col: if to.col == 7 { 6 } else { 2 },
+        };
+    }
+    return bs.move_from_string(&format!("{}{}{}", from.str(), to.str(), promotion));
+}
+
+/// the opposite of decode_move, castling becomes the king taking its own rook
+pub fn encode_move(bs: &BoardState, chess_move: Move) -> u16 {
+    let mut to = chess_move.to;
+    if bs.get_piece_at_position(chess_move.from) & PIECE_MASK == KING && (chess_move.from.col - to.col).abs() == 2 {
+        to.col = if to.col == 6 { 7 } else { 0 };
+    }
+    let promotion = match chess_move.promotion & PIECE_MASK {
+        KNIGHT => 1,
+        BISHOP => 2,
+        ROOK => 3,
+        QUEEN => 4,
+        _ => 0,
+    };
+    let from = Position::index_from_position(chess_move.from) as u16;
+    let to = Position::index_from_position(to) as u16;
+    return promotion << 12 | from << 6 | to;
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use crate::board_state::{BoardState, Move};
+
+/// what a chess problem asks for, the side to move begins
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stipulation {
+    /// mate in this many moves against every defense, written "#2"
+    Mate(u32),
+    /// both sides work together so the side not to move mates on its last move, written "h#2"
+    Helpmate(u32),
+    /// the side to move forces the other side to mate it in this many moves, the other side tries not to, written "s#2"
+    Selfmate(u32),
+}
+
+impl Stipulation {
+    /// "#2", "h#2" or "s#2", None for anything else
+    pub fn parse(text: &str) -> Option<Stipulation> {
+        let text = text.trim();
+        let (kind, moves) = text.split_once('#')?;
+        let moves: u32 = moves.parse().ok()?;
+        if moves == 0 {
+            return None;
+        }
+        return match kind {
+            "" => Some(Stipulation::Mate(moves)),
+            "h" => Some(Stipulation::Helpmate(moves)),
+            "s" => Some(Stipulation::Selfmate(moves)),
+            _ => None,
+        };
+    }
+
+    pub fn str(&self) -> String {
+        return match self {
+            Stipulation::Mate(moves) => format!("#{}", moves),
+            Stipulation::Helpmate(moves) => format!("h#{}", moves),
+            Stipulation::Selfmate(moves) => format!("s#{}", moves),
+        };
+    }
+
+    pub fn moves(&self) -> u32 {
+        return match self {
+            Stipulation::Mate(moves) | Stipulation::Helpmate(moves) | Stipulation::Selfmate(moves) => *moves,
+        };
+    }
+
+    /// the same kind of problem with fewer or more moves
+    fn with_moves(&self, moves: u32) -> Stipulation {
+        return match self {
+            Stipulation::Mate(_) => Stipulation::Mate(moves),
+            Stipulation::Helpmate(_) => Stipulation::Helpmate(moves),
+            Stipulation::Selfmate(_) => Stipulation::Selfmate(moves),
+        };
+    }
+}
+
+/// a move of the solution and the moves that answer it, the tree ends with the mates
+#[derive(Clone, Debug)]
+pub struct SolutionNode {
+    pub chess_move: Move,
+    pub replies: Vec<SolutionNode>,
+}
+
+pub struct Solution {
+    pub stipulation: Stipulation,
+    /// the first moves that solve the problem
+    pub keys: Vec<SolutionNode>,
+    pub positions_searched: u64,
+}
+
+impl Solution {
+    /// more than one key, for helpmates more than one line
+    pub fn is_cooked(&self) -> bool {
+        return self.solution_count() > 1;
+    }
+
+    pub fn solution_count(&self) -> usize {
+        if let Stipulation::Helpmate(_) = self.stipulation {
+            return self.keys.iter().map(count_lines).sum();
+        }
+        return self.keys.len();
+    }
+
+    /// the solution tree with one move per line, answers indented below the move they answer
+    pub fn str(&self, bs: &BoardState) -> String {
+        let mut lines = Vec::new();
+        for key in &self.keys {
+            write_node(bs, key, 1, true, 0, &mut lines);
+        }
+        return lines.join("\n");
+    }
+}
+
+fn count_lines(node: &SolutionNode) -> usize {
+    if node.replies.is_empty() {
+        return 1;
+    }
+    return node.replies.iter().map(count_lines).sum();
+}
+
+fn write_node(bs: &BoardState, node: &SolutionNode, move_number: u32, is_key: bool, indentation: usize, lines: &mut Vec<String>) {
+    let new_state = bs.make_move(node.chess_move);
+    // the attacker's moves and the answers alternate
+    let is_attack = indentation.is_multiple_of(2);
+    let number = if is_attack {
+        format!("{}.", move_number)
+    } else {
+        format!("{}...", move_number)
+    };
+    let mark = if new_state.is_checkmate() {
+        "#"
+    } else if new_state.is_in_check() {
+        "+"
+    } else {
+        ""
+    };
+    let key_mark = if is_key { "!" } else { "" };
+    lines.push(format!(
+        "{}{} {}{}{}",
+        "    ".repeat(indentation),
+        number,
+        node.chess_move.str(),
+        mark,
+        key_mark
+    ));
+    let next_number = if is_attack { move_number } else { move_number + 1 };
+    for reply in &node.replies {
+        write_node(&new_state, reply, next_number, false, indentation + 1, lines);
+    }
+}
+
+/// depth-first search over every move of both sides, positions already proven or refuted are remembered
+pub struct Solver {
+    // whether the side to move of the position can fulfill the stipulation
+    known: HashMap<(Stipulation, u64), bool>,
+    positions_searched: u64,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        return Solver::new();
+    }
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        return Solver {
+            known: HashMap::new(),
+            positions_searched: 0,
+        };
+    }
+
+    /// every key of the problem with the full solution tree, shorter solutions count as well except in helpmates,
+    /// which have to take all their moves
+    pub fn solve(&mut self, bs: &BoardState, stipulation: Stipulation) -> Solution {
+        let keys = match stipulation {
+            Stipulation::Helpmate(moves) => self.help_lines(bs, 2 * moves),
+            _ => self.winning_moves(bs, stipulation),
+        };
+        return Solution {
+            stipulation,
+            keys,
+            positions_searched: self.positions_searched,
+        };
+    }
+
+    /// whether the side to move fulfills the mate or selfmate stipulation
+    fn achieves(&mut self, bs: &BoardState, stipulation: Stipulation) -> bool {
+        let key = (stipulation, bs.zobrist_key());
+        if let Some(known) = self.known.get(&key) {
+            return *known;
+        }
+        self.positions_searched += 1;
+        let mut legal_moves = bs.get_legal_moves();
+        // checks are the likeliest mates
+        if let Stipulation::Mate(_) = stipulation {
+            legal_moves.sort_by_key(|chess_move| !bs.make_move(*chess_move).is_in_check());
+        }
+        let achieved = legal_moves
+            .into_iter()
+            .any(|chess_move| self.forces_after(&bs.make_move(chess_move), stipulation));
+        self.known.insert(key, achieved);
+        return achieved;
+    }
+
+    /// whether every defense in the position after the attacker's move leads to the stipulated mate, the move
+    /// itself counts as the first one
+    fn forces_after(&mut self, bs: &BoardState, stipulation: Stipulation) -> bool {
+        let moves = stipulation.moves();
+        // the last move of a direct mate has to give check, which is much faster to see than the replies
+        if let (Stipulation::Mate(1), false) = (stipulation, bs.is_in_check()) {
+            return false;
+        }
+        self.positions_searched += 1;
+        let defenses = bs.get_legal_moves();
+        if defenses.is_empty() {
+            // mating the defender only solves a direct mate, in a selfmate the defender has to be the one mating
+            return matches!(stipulation, Stipulation::Mate(_)) && bs.is_in_check();
+        }
+        for defense in defenses {
+            let defended = bs.make_move(defense);
+            let is_achieved = match stipulation {
+                Stipulation::Selfmate(_) if defended.is_checkmate() => true,
+                _ if moves == 1 => false,
+                _ => self.achieves(&defended, stipulation.with_moves(moves - 1)),
+            };
+            if !is_achieved {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// every move that fulfills the stipulation with its tree
+    fn winning_moves(&mut self, bs: &BoardState, stipulation: Stipulation) -> Vec<SolutionNode> {
+        let mut nodes = Vec::new();
+        for chess_move in bs.get_legal_moves() {
+            if self.forces_after(&bs.make_move(chess_move), stipulation) {
+                nodes.push(self.node_after_attack(bs, chess_move, stipulation));
+            }
+        }
+        return nodes;
+    }
+
+    /// the attacker's move with every defense and the fastest continuations that answer them
+    fn node_after_attack(&mut self, bs: &BoardState, chess_move: Move, stipulation: Stipulation) -> SolutionNode {
+        let new_state = bs.make_move(chess_move);
+        let moves = stipulation.moves();
+        let mut replies = Vec::new();
+        for defense in new_state.get_legal_moves() {
+            let defended = new_state.make_move(defense);
+            // a selfmate is over once the defender mates
+            if defended.is_checkmate() || moves == 1 {
+                replies.push(SolutionNode {
+                    chess_move: defense,
+                    replies: Vec::new(),
+                });
+                continue;
+            }
+            let fastest = (1..moves)
+                .find(|remaining| self.achieves(&defended, stipulation.with_moves(*remaining)))
+                .unwrap_or(moves - 1);
+            replies.push(SolutionNode {
+                chess_move: defense,
+                replies: self.winning_moves(&defended, stipulation.with_moves(fastest)),
+            });
+        }
+        return SolutionNode { chess_move, replies };
+    }
+
+    /// every sequence of exactly this many plies that ends in mate, both sides help
+    fn help_lines(&mut self, bs: &BoardState, plies: u32) -> Vec<SolutionNode> {
+        let mut nodes = Vec::new();
+        for chess_move in bs.get_legal_moves() {
+            let new_state = bs.make_move(chess_move);
+            if plies == 1 {
+                if new_state.is_checkmate() {
+                    nodes.push(SolutionNode {
+                        chess_move,
+                        replies: Vec::new(),
+                    });
+                }
+                continue;
+            }
+            if self.can_help(&new_state, plies - 1) {
+                nodes.push(SolutionNode {
+                    chess_move,
+                    replies: self.help_lines(&new_state, plies - 1),
+                });
+            }
+        }
+        return nodes;
+    }
+
+    /// whether some sequence of exactly this many plies ends in mate
+    fn can_help(&mut self, bs: &BoardState, plies: u32) -> bool {
+        // the plies are stored as a helpmate in that many moves, only this function uses those entries
+        let key = (Stipulation::Helpmate(plies), bs.zobrist_key());
+        if let Some(known) = self.known.get(&key) {
+            return *known;
+        }
+        self.positions_searched += 1;
+        let mut possible = false;
+        for chess_move in bs.get_legal_moves() {
+            let new_state = bs.make_move(chess_move);
+            possible = if plies == 1 {
+                new_state.is_checkmate()
+            } else {
+                self.can_help(&new_state, plies - 1)
+            };
+            if possible {
+                break;
+            }
+        }
+        self.known.insert(key, possible);
+        return possible;
+    }
+}
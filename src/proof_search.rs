@@ -0,0 +1,243 @@
+use crate::board_state::{BoardState, Move, Position, COLOR_MASK, KING, PIECE_MASK, PIECE_VALUES};
+
+/// proof and disproof numbers are capped here, a node with this number can't be proven or disproven anymore
+const INFINITE: u32 = u32::MAX;
+pub const DEFAULT_MAX_PLIES: u32 = 12;
+pub const DEFAULT_MAX_NODES: usize = 1_000_000;
+/// winning a piece is the default material goal
+pub const DEFAULT_MATERIAL_GAIN: i32 = 300;
+
+/// what the side to move tries to prove it can force
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    Mate,
+    /// being ahead by this many centipawns more than at the start after the opponent had its reply
+    Material(i32),
+}
+
+impl Goal {
+    /// "mate", "material" or "material:500"
+    pub fn parse(text: &str) -> Option<Goal> {
+        return match text.trim().split_once(':') {
+            None if text.trim() == "mate" => Some(Goal::Mate),
+            None if text.trim() == "material" => Some(Goal::Material(DEFAULT_MATERIAL_GAIN)),
+            Some(("material", gain)) => gain.parse().ok().filter(|gain| *gain > 0).map(Goal::Material),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofResult {
+    Proven,
+    Disproven,
+    /// the node limit was reached first
+    Unknown,
+}
+
+pub struct ProofSearchResult {
+    pub result: ProofResult,
+    /// the positions in the search tree
+    pub nodes: usize,
+    /// the positions of the smallest proof in the tree, 0 unless the goal was proven
+    pub proof_tree_size: usize,
+    /// the attacker's best moves and the defender's longest resistance
+    pub line: Vec<Move>,
+}
+
+/// a position of the tree, at OR nodes the attacker is to move and one child has to be proven, at AND nodes every one,
+/// only the move is kept, the position is played again from the root when the search comes back to the node
+struct Node {
+    chess_move: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    proof: u32,
+    disproof: u32,
+    is_or: bool,
+    ply: u32,
+}
+
+/// proof-number search: the tree grows at the most proving node, the leaf that is cheapest to prove or disprove the
+/// root through, until the root is decided, positions deeper than the ply limit count as failures
+pub fn prove(bs: &BoardState, goal: Goal, max_plies: u32, max_nodes: usize) -> ProofSearchResult {
+    let attacker = bs.color_to_move();
+    let start_balance = material_balance(bs, attacker);
+    let mut nodes = vec![Node {
+        chess_move: None,
+        parent: None,
+        children: Vec::new(),
+        proof: 1,
+        disproof: 1,
+        is_or: true,
+        ply: 0,
+    }];
+    // the root is never a goal itself, the attacker has to do something
+    if bs.get_legal_moves().is_empty() {
+        nodes[0].proof = INFINITE;
+        nodes[0].disproof = 0;
+    }
+    while nodes[0].proof != 0 && nodes[0].disproof != 0 && nodes.len() < max_nodes {
+        let (leaf, leaf_state) = most_proving_node(&nodes, bs);
+        expand(&mut nodes, leaf, &leaf_state, goal, attacker, start_balance, max_plies);
+        update_ancestors(&mut nodes, leaf);
+    }
+    let result = match (nodes[0].proof, nodes[0].disproof) {
+        (0, _) => ProofResult::Proven,
+        (_, 0) => ProofResult::Disproven,
+        _ => ProofResult::Unknown,
+    };
+    let (proof_tree_size, line) = if result == ProofResult::Proven {
+        (proof_size(&nodes, 0), proof_line(&nodes, 0))
+    } else {
+        (0, Vec::new())
+    };
+    return ProofSearchResult {
+        result,
+        nodes: nodes.len(),
+        proof_tree_size,
+        line,
+    };
+}
+
+/// the material of the color minus the other side's
+fn material_balance(bs: &BoardState, color: i32) -> i32 {
+    let mut balance = 0;
+    for square in 0..64 {
+        let piece = bs.get_piece_at_position(Position::position_from_indices(square));
+        if piece & PIECE_MASK == KING {
+            continue;
+        }
+        let value = PIECE_VALUES[(piece & PIECE_MASK) as usize];
+        balance += if piece & COLOR_MASK == color { value } else { -value };
+    }
+    return balance;
+}
+
+/// the node and its position, played from the root along the way
+fn most_proving_node(nodes: &[Node], root: &BoardState) -> (usize, BoardState) {
+    let mut index = 0;
+    let mut bs = root.clone();
+    while !nodes[index].children.is_empty() {
+        let node = &nodes[index];
+        let number = |child: &usize| if node.is_or { nodes[*child].proof } else { nodes[*child].disproof };
+        index = *node.children.iter().min_by_key(|child| number(child)).expect("the node has children");
+        bs = bs.make_move(nodes[index].chess_move.expect("only the root has no move"));
+    }
+    return (index, bs);
+}
+
+fn expand(nodes: &mut Vec<Node>, index: usize, parent_state: &BoardState, goal: Goal, attacker: i32, start_balance: i32, max_plies: u32) {
+    let parent_is_or = nodes[index].is_or;
+    let ply = nodes[index].ply + 1;
+    for chess_move in parent_state.get_legal_moves() {
+        let bs = parent_state.make_move(chess_move);
+        let legal_moves = bs.get_legal_moves();
+        let is_attacker_to_move = bs.color_to_move() == attacker;
+        let gained = material_balance(&bs, attacker) - start_balance;
+        let is_proven = match goal {
+            _ if legal_moves.is_empty() => bs.is_in_check() && !is_attacker_to_move,
+            Goal::Mate => false,
+            // the defender had its chance to take back
+            Goal::Material(gain) => is_attacker_to_move && gained >= gain,
+        };
+        let is_disproven = !is_proven && (legal_moves.is_empty() || ply >= max_plies);
+        let (proof, disproof) = if is_proven {
+            (0, INFINITE)
+        } else if is_disproven {
+            (INFINITE, 0)
+        } else if is_attacker_to_move {
+            (1, 1)
+        } else {
+            // the more replies the defender has, the harder it is to prove they all fail
+            (legal_moves.len() as u32, 1)
+        };
+        let child = nodes.len();
+        nodes[index].children.push(child);
+        nodes.push(Node {
+            chess_move: Some(chess_move),
+            parent: Some(index),
+            children: Vec::new(),
+            proof,
+            disproof,
+            is_or: !parent_is_or,
+            ply,
+        });
+    }
+    // without children the numbers of the leaf are final already
+    if nodes[index].children.is_empty() {
+        let (proof, disproof) = if nodes[index].is_or { (INFINITE, 0) } else { (0, INFINITE) };
+        nodes[index].proof = proof;
+        nodes[index].disproof = disproof;
+    }
+}
+
+/// an OR node needs one proven child and all children disproven, an AND node the other way around
+fn update_ancestors(nodes: &mut [Node], leaf: usize) {
+    let mut current = Some(leaf);
+    while let Some(index) = current {
+        if !nodes[index].children.is_empty() {
+            let proofs = nodes[index].children.iter().map(|child| nodes[*child].proof);
+            let disproofs = nodes[index].children.iter().map(|child| nodes[*child].disproof);
+            let (proof, disproof) = if nodes[index].is_or {
+                (
+                    proofs.min().unwrap_or(INFINITE),
+                    disproofs.fold(0, |sum: u32, disproof| sum.saturating_add(disproof)),
+                )
+            } else {
+                (
+                    proofs.fold(0, |sum: u32, proof| sum.saturating_add(proof)),
+                    disproofs.min().unwrap_or(INFINITE),
+                )
+            };
+            nodes[index].proof = proof;
+            nodes[index].disproof = disproof;
+        }
+        current = nodes[index].parent;
+    }
+}
+
+/// the positions of the smallest proof below the proven node
+fn proof_size(nodes: &[Node], index: usize) -> usize {
+    let node = &nodes[index];
+    if node.children.is_empty() {
+        return 1;
+    }
+    if node.is_or {
+        let smallest = node
+            .children
+            .iter()
+            .filter(|child| nodes[**child].proof == 0)
+            .map(|child| proof_size(nodes, *child))
+            .min();
+        return 1 + smallest.unwrap_or(0);
+    }
+    return 1 + node.children.iter().map(|child| proof_size(nodes, *child)).sum::<usize>();
+}
+
+/// the plies to the goal when the attacker hurries and the defender holds out
+fn proof_depth(nodes: &[Node], index: usize) -> usize {
+    let node = &nodes[index];
+    let depths = node
+        .children
+        .iter()
+        .filter(|child| nodes[**child].proof == 0)
+        .map(|child| proof_depth(nodes, *child));
+    let depth = if node.is_or { depths.min() } else { depths.max() };
+    return depth.map_or(0, |depth| depth + 1);
+}
+
+fn proof_line(nodes: &[Node], index: usize) -> Vec<Move> {
+    let node = &nodes[index];
+    let proven_children = node.children.iter().filter(|child| nodes[**child].proof == 0);
+    let next = if node.is_or {
+        proven_children.min_by_key(|child| proof_depth(nodes, **child))
+    } else {
+        proven_children.max_by_key(|child| proof_depth(nodes, **child))
+    };
+    let Some(next) = next else {
+        return Vec::new();
+    };
+    let mut line = vec![nodes[*next].chess_move.expect("only the root has no move")];
+    line.extend(proof_line(nodes, *next));
+    return line;
+}
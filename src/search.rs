@@ -0,0 +1,885 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board_state::{BoardState, Move, Position, COLOR_MASK, KING, NOTHING, PAWN, PIECE_MASK};
+use crate::evaluation::evaluate_with_pawn_table;
+use crate::move_ordering::{is_tactical, MovePicker, OrderingTables, MAX_PLY};
+use crate::nnue::{Accumulator, Network};
+use crate::pawn_structure::PawnTable;
+use crate::syzygy::{Tablebases, Wdl};
+use crate::tablebase::{Dtm, EndgameTables};
+use crate::transposition_table::{Bound, TranspositionTable};
+
+pub const INFINITY: i32 = 32000;
+pub const MATE_SCORE: i32 = 31000;
+/// scores above this are mates, the difference to MATE_SCORE is the distance in plies
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1000;
+/// positions the tablebases say are won score this minus the ply, below the mates that the search has seen
+pub const TABLEBASE_WIN: i32 = MATE_THRESHOLD - 1000;
+
+pub const DEFAULT_HASH_MEGABYTES: usize = 16;
+pub const MAX_HASH_MEGABYTES: usize = 65536;
+pub const MAX_THREADS: usize = 256;
+pub const MAX_MULTI_PV: usize = 256;
+/// the UCI names of the search switches
+pub const SWITCHES: [&str; 7] = [
+    "MoveOrdering",
+    "NullMove",
+    "LateMoveReductions",
+    "ReverseFutilityPruning",
+    "FutilityPruning",
+    "Razoring",
+    "CheckExtensions",
+];
+
+const REVERSE_FUTILITY_MARGIN: i32 = 80;
+const FUTILITY_MARGIN: i32 = 120;
+const RAZORING_MARGIN: i32 = 250;
+// half the width of the first window around the score of the previous iteration
+const ASPIRATION_WINDOW: i32 = 30;
+// the clock is only looked at every so many nodes, reading it is slow
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// called with the result of every completed iteration
+pub type IterationCallback = Box<dyn FnMut(&SearchResult) + Send>;
+
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: i32,
+    pub nodes: u64,
+    /// permille of the transposition table used by the search
+    pub hashfull: usize,
+    /// the principal variation, the line both sides are expected to play
+    pub pv: Vec<Move>,
+    /// the best lines starting with different moves, best first, as many as MultiPV asks for
+    pub lines: Vec<SearchLine>,
+}
+
+impl SearchResult {
+    pub fn score_string(&self) -> String {
+        return score_string(self.score);
+    }
+
+    pub fn pv_string(&self) -> String {
+        return pv_string(&self.pv);
+    }
+
+    /// the answer the engine expects to its best move, the position after it is searched while the opponent thinks
+    pub fn ponder_move(&self) -> Option<Move> {
+        return self.pv.get(1).copied();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+impl SearchLine {
+    pub fn score_string(&self) -> String {
+        return score_string(self.score);
+    }
+
+    pub fn pv_string(&self) -> String {
+        return pv_string(&self.pv);
+    }
+}
+
+/// the score as UCI reports it, either "cp <centipawns>" or "mate <moves>" with negative moves when getting mated
+pub fn score_string(score: i32) -> String {
+    if score >= MATE_THRESHOLD {
+        return format!("mate {}", (MATE_SCORE - score + 1) / 2);
+    }
+    if score <= -MATE_THRESHOLD {
+        return format!("mate -{}", (MATE_SCORE + score) / 2);
+    }
+    return format!("cp {}", score);
+}
+
+fn pv_string(pv: &[Move]) -> String {
+    return pv.iter().map(|m| m.str()).collect::<Vec<String>>().join(" ");
+}
+
+/// when the search has to stop, the deepest completed iteration is used
+#[derive(Clone, Copy, Debug)]
+pub struct SearchLimits {
+    pub depth: i32,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+    /// pondering on the opponent's time, the clock is paused until SearchClock::start is called
+    pub ponder: bool,
+}
+
+impl SearchLimits {
+    /// searches until it is stopped
+    pub fn infinite() -> SearchLimits {
+        return SearchLimits {
+            depth: MAX_PLY as i32 - 1,
+            nodes: None,
+            time: None,
+            ponder: false,
+        };
+    }
+
+    pub fn depth(depth: i32) -> SearchLimits {
+        return SearchLimits {
+            depth,
+            ..SearchLimits::infinite()
+        };
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        return SearchLimits {
+            nodes: Some(nodes),
+            ..SearchLimits::infinite()
+        };
+    }
+
+    pub fn time(time: Duration) -> SearchLimits {
+        return SearchLimits {
+            time: Some(time),
+            ..SearchLimits::infinite()
+        };
+    }
+}
+
+/// the time the current search has been running, it can be paused while pondering and started when the opponent
+/// plays the expected move
+pub struct SearchClock {
+    epoch: Instant,
+    // milliseconds from the epoch to the start of the search, u64::MAX while the clock is paused
+    start: AtomicU64,
+}
+
+impl SearchClock {
+    fn new() -> SearchClock {
+        return SearchClock {
+            epoch: Instant::now(),
+            start: AtomicU64::new(u64::MAX),
+        };
+    }
+
+    pub fn start(&self) {
+        self.start.store(self.epoch.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn pause(&self) {
+        self.start.store(u64::MAX, Ordering::Relaxed);
+    }
+
+    /// None while the clock is paused
+    pub fn elapsed(&self) -> Option<Duration> {
+        let start = self.start.load(Ordering::Relaxed);
+        if start == u64::MAX {
+            return None;
+        }
+        return Some(Duration::from_millis((self.epoch.elapsed().as_millis() as u64).saturating_sub(start)));
+    }
+}
+
+/// switches for the parts of the search, so the effect of each of them can be measured
+#[derive(Clone, Copy, Debug)]
+pub struct SearchOptions {
+    pub move_ordering: bool,
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility_pruning: bool,
+    pub futility_pruning: bool,
+    pub razoring: bool,
+    pub check_extensions: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        return SearchOptions {
+            move_ordering: true,
+            null_move: true,
+            late_move_reductions: true,
+            reverse_futility_pruning: true,
+            futility_pruning: true,
+            razoring: true,
+            check_extensions: true,
+        };
+    }
+}
+
+impl SearchOptions {
+    /// the switch with the given UCI option name
+    pub fn switch_mut(&mut self, name: &str) -> Option<&mut bool> {
+        return match name.to_lowercase().as_str() {
+            "moveordering" => Some(&mut self.move_ordering),
+            "nullmove" => Some(&mut self.null_move),
+            "latemovereductions" => Some(&mut self.late_move_reductions),
+            "reversefutilitypruning" => Some(&mut self.reverse_futility_pruning),
+            "futilitypruning" => Some(&mut self.futility_pruning),
+            "razoring" => Some(&mut self.razoring),
+            "checkextensions" => Some(&mut self.check_extensions),
+            _ => None,
+        };
+    }
+}
+
+/// runs the search on one or more threads, lazy SMP: all threads search the same position and only share
+/// the transposition table, the helpers fill it with results the main thread can use
+pub struct Searcher {
+    pub options: SearchOptions,
+    transposition_table: Arc<TranspositionTable>,
+    // the first one is the main thread, the only one whose result is used
+    threads: Vec<SearchThread>,
+    multi_pv: usize,
+    network: Option<Arc<Network>>,
+    use_nnue: bool,
+    tablebases: Option<Arc<Tablebases>>,
+    endgame_tables: Option<Arc<EndgameTables>>,
+    stop: Arc<AtomicBool>,
+    clock: Arc<SearchClock>,
+    iteration_callback: Option<IterationCallback>,
+    // keys of the earlier positions of the game
+    game_history: Vec<u64>,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        return Searcher::new();
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Searcher {
+        let transposition_table = Arc::new(TranspositionTable::new(DEFAULT_HASH_MEGABYTES));
+        return Searcher {
+            options: SearchOptions::default(),
+            threads: vec![SearchThread::new(0, Arc::clone(&transposition_table))],
+            transposition_table,
+            multi_pv: 1,
+            network: None,
+            use_nnue: false,
+            tablebases: None,
+            endgame_tables: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            clock: Arc::new(SearchClock::new()),
+            iteration_callback: None,
+            game_history: Vec::new(),
+        };
+    }
+
+    /// sets an engine option by its UCI name
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if let Some(switch) = self.options.switch_mut(name) {
+            *switch = value.trim().parse().map_err(|_| format!("Not a valid value for {}: {}", name, value))?;
+            return Ok(());
+        }
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let megabytes: usize = value.trim().parse().map_err(|_| format!("Not a valid hash size: {}", value))?;
+                if megabytes == 0 || megabytes > MAX_HASH_MEGABYTES {
+                    return Err(format!("Hash has to be between 1 and {} MB", MAX_HASH_MEGABYTES));
+                }
+                self.transposition_table = Arc::new(TranspositionTable::new(megabytes));
+            }
+            "threads" => {
+                let threads: usize = value.trim().parse().map_err(|_| format!("Not a valid number of threads: {}", value))?;
+                if threads == 0 || threads > MAX_THREADS {
+                    return Err(format!("Threads has to be between 1 and {}", MAX_THREADS));
+                }
+                self.set_threads(threads);
+            }
+            "multipv" => {
+                let lines: usize = value.trim().parse().map_err(|_| format!("Not a valid number of lines: {}", value))?;
+                if lines == 0 || lines > MAX_MULTI_PV {
+                    return Err(format!("MultiPV has to be between 1 and {}", MAX_MULTI_PV));
+                }
+                self.multi_pv = lines;
+            }
+            "evalfile" => {
+                self.network = Some(Arc::new(Network::load(value.trim())?));
+            }
+            // only switches the network on, GUIs send the options in any order so this may come before EvalFile,
+            // without a network the handcrafted evaluation stays in use
+            "usennue" => {
+                self.use_nnue = value.trim().parse().map_err(|_| format!("Not a valid value for {}: {}", name, value))?;
+            }
+            "syzygypath" => {
+                let path = value.trim();
+                self.tablebases = if path.is_empty() || path == "<empty>" {
+                    None
+                } else {
+                    Some(Arc::new(Tablebases::open(path)?))
+                };
+            }
+            "tablebasepath" => {
+                let path = value.trim();
+                self.endgame_tables = if path.is_empty() || path == "<empty>" {
+                    None
+                } else {
+                    Some(Arc::new(EndgameTables::load(path)?))
+                };
+            }
+            _ => return Err(format!("Unknown option: {}", name)),
+        }
+        return Ok(());
+    }
+
+    /// with a single thread the search is deterministic, the same position and limits always give the same result
+    pub fn set_threads(&mut self, threads: usize) {
+        while self.threads.len() > threads {
+            self.threads.pop();
+        }
+        while self.threads.len() < threads {
+            self.threads
+                .push(SearchThread::new(self.threads.len(), Arc::clone(&self.transposition_table)));
+        }
+    }
+
+    /// forget everything learned in previous searches, e.g. when a new game starts
+    pub fn clear(&mut self) {
+        self.transposition_table.clear();
+        for thread in self.threads.iter_mut() {
+            thread.ordering.clear();
+        }
+    }
+
+    /// the keys of the positions played before the one that will be searched, so repetitions are recognized
+    pub fn set_game_history(&mut self, keys: Vec<u64>) {
+        self.game_history = keys;
+    }
+
+    pub fn set_iteration_callback(&mut self, callback: IterationCallback) {
+        self.iteration_callback = Some(callback);
+    }
+
+    /// setting the flag stops a running search, the result of the deepest completed iteration is returned, the flag is
+    /// cleared when the search returns so setting it right before a search starts isn't lost
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        return Arc::clone(&self.stop);
+    }
+
+    /// a search with a time limit runs until the clock says the time is up, a pondering search starts with a paused
+    /// clock, pausing has to happen before the search so a start from another thread can't be lost
+    pub fn clock(&self) -> Arc<SearchClock> {
+        return Arc::clone(&self.clock);
+    }
+
+    /// iterative deepening on all threads until one of the limits is reached
+    pub fn search(&mut self, bs: &BoardState, limits: &SearchLimits) -> SearchResult {
+        if !limits.ponder {
+            self.clock.start();
+        }
+        self.transposition_table.new_search();
+        // in a tablebase position only the moves that keep the result are searched
+        let legal_moves = bs.get_legal_moves();
+        // the generated tables know the distance to mate, the Syzygy tables only keep the result
+        let best_moves = match self.endgame_tables.as_ref().and_then(|endgame_tables| endgame_tables.best_moves(bs)) {
+            Some((best_moves, _)) => Some(best_moves),
+            None => self.tablebases.as_ref().and_then(|tablebases| tablebases.root_moves(bs)),
+        };
+        let losing_root_moves: Vec<Move> = match best_moves {
+            Some(best_moves) => legal_moves.into_iter().filter(|chess_move| !best_moves.contains(chess_move)).collect(),
+            None => Vec::new(),
+        };
+        let total_nodes = Arc::new(AtomicU64::new(0));
+        for thread in self.threads.iter_mut() {
+            thread.options = self.options;
+            thread.transposition_table = Arc::clone(&self.transposition_table);
+            thread.stop = Arc::clone(&self.stop);
+            thread.total_nodes = Arc::clone(&total_nodes);
+            thread.max_nodes = limits.nodes;
+            thread.clock = Arc::clone(&self.clock);
+            thread.max_time = limits.time;
+            thread.multi_pv = self.multi_pv;
+            thread.set_network(if self.use_nnue { self.network.clone() } else { None });
+            thread.key_history = self.game_history.clone();
+            thread.tablebases = self.tablebases.clone();
+            thread.endgame_tables = self.endgame_tables.clone();
+            thread.losing_root_moves = losing_root_moves.clone();
+            thread.ordering.age();
+        }
+        let stop = &self.stop;
+        let callback = &mut self.iteration_callback;
+        let (main_thread, helper_threads) = self.threads.split_first_mut().expect("there is always a main thread");
+        let mut result = thread::scope(|scope| {
+            for helper in helper_threads.iter_mut() {
+                scope.spawn(|| helper.iterative_deepening(bs, limits, None));
+            }
+            let result = main_thread.iterative_deepening(bs, limits, callback.as_mut());
+            // the helpers only search as long as the main thread does
+            stop.store(true, Ordering::Relaxed);
+            result
+        });
+        self.stop.store(false, Ordering::Relaxed);
+        result.nodes = total_nodes.load(Ordering::Relaxed);
+        return result;
+    }
+}
+
+/// the state of one search thread, the history tables are kept between searches
+struct SearchThread {
+    id: usize,
+    options: SearchOptions,
+    transposition_table: Arc<TranspositionTable>,
+    ordering: OrderingTables,
+    pawn_table: PawnTable,
+    // without a network the handcrafted evaluation is used
+    network: Option<Arc<Network>>,
+    // the accumulator of the position at every ply
+    accumulators: Vec<Accumulator>,
+    stop: Arc<AtomicBool>,
+    // the nodes of all threads together
+    total_nodes: Arc<AtomicU64>,
+    max_nodes: Option<u64>,
+    clock: Arc<SearchClock>,
+    max_time: Option<Duration>,
+    // the nodes of this thread since the clock was last looked at
+    nodes_since_time_check: u64,
+    multi_pv: usize,
+    // moves at the root that already lead one of the multi pv lines of the current iteration
+    excluded_root_moves: Vec<Move>,
+    tablebases: Option<Arc<Tablebases>>,
+    endgame_tables: Option<Arc<EndgameTables>>,
+    // root moves that give away the result the tablebases promise, they are never searched
+    losing_root_moves: Vec<Move>,
+    // the principal variation of every ply, each one continues the one of the next ply
+    pv_table: Vec<Vec<Move>>,
+    // keys of all positions from the start of the game to the current node, used to detect repetitions
+    key_history: Vec<u64>,
+}
+
+impl SearchThread {
+    fn new(id: usize, transposition_table: Arc<TranspositionTable>) -> SearchThread {
+        return SearchThread {
+            id,
+            options: SearchOptions::default(),
+            transposition_table,
+            ordering: OrderingTables::new(),
+            pawn_table: PawnTable::new(),
+            network: None,
+            accumulators: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            total_nodes: Arc::new(AtomicU64::new(0)),
+            max_nodes: None,
+            clock: Arc::new(SearchClock::new()),
+            max_time: None,
+            nodes_since_time_check: 0,
+            multi_pv: 1,
+            excluded_root_moves: Vec::new(),
+            tablebases: None,
+            endgame_tables: None,
+            losing_root_moves: Vec::new(),
+            pv_table: vec![Vec::new(); MAX_PLY + 1],
+            key_history: Vec::new(),
+        };
+    }
+
+    fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.accumulators = match &network {
+            Some(network) => vec![network.new_accumulator(); MAX_PLY + 1],
+            None => Vec::new(),
+        };
+        self.network = network;
+    }
+
+    fn iterative_deepening(&mut self, bs: &BoardState, limits: &SearchLimits, mut callback: Option<&mut IterationCallback>) -> SearchResult {
+        if let Some(network) = &self.network {
+            network.refresh(bs, &mut self.accumulators[0]);
+        }
+        let root_moves: Vec<Move> = bs
+            .get_legal_moves()
+            .into_iter()
+            .filter(|chess_move| !self.losing_root_moves.contains(chess_move))
+            .collect();
+        let mut result = SearchResult {
+            best_move: root_moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            hashfull: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+        };
+        if root_moves.is_empty() {
+            result.score = if bs.is_in_check() { -MATE_SCORE } else { 0 };
+            return result;
+        }
+        let line_count = self.multi_pv.min(root_moves.len());
+        // helpers start at different depths so the threads don't all search the same thing at the same time
+        let first_depth = 1 + (self.id % 2) as i32;
+        'iterations: for depth in first_depth..=limits.depth.min(MAX_PLY as i32 - 1) {
+            // every line is searched without the moves of the lines before it
+            let mut lines: Vec<SearchLine> = Vec::new();
+            self.excluded_root_moves.clear();
+            for line_index in 0..line_count {
+                let previous_score = result.lines.get(line_index).map(|line| line.score).unwrap_or(result.score);
+                let score = self.aspiration_search(bs, depth, previous_score);
+                if self.is_stopped() {
+                    break 'iterations;
+                }
+                let pv = self.pv_table[0].clone();
+                let Some(first) = pv.first() else {
+                    break;
+                };
+                self.excluded_root_moves.push(*first);
+                lines.push(SearchLine { score, pv });
+            }
+            self.excluded_root_moves.clear();
+            if lines.is_empty() {
+                break;
+            }
+            lines.sort_by_key(|line| -line.score);
+            result = SearchResult {
+                best_move: lines[0].pv.first().copied(),
+                score: lines[0].score,
+                depth,
+                nodes: self.total_nodes.load(Ordering::Relaxed),
+                hashfull: self.transposition_table.hashfull(),
+                pv: lines[0].pv.clone(),
+                lines,
+            };
+            if let Some(callback) = callback.as_mut() {
+                callback(&result);
+            }
+            if line_count == 1 && result.score.abs() >= MATE_THRESHOLD {
+                // a shorter mate has been found already
+                break;
+            }
+            // the next iteration takes longer than all before it together, it most likely wouldn't finish in time
+            if let (Some(max_time), Some(elapsed)) = (limits.time, self.clock.elapsed()) {
+                if elapsed * 2 >= max_time {
+                    break;
+                }
+            }
+        }
+        self.excluded_root_moves.clear();
+        return result;
+    }
+
+    /// the score most likely stays close to the one of the previous iteration, a small window around it cuts more,
+    /// if the score lies outside of it the window gets wider until it fits
+    fn aspiration_search(&mut self, bs: &BoardState, depth: i32, previous_score: i32) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = (-INFINITY, INFINITY);
+        if depth >= 4 && previous_score.abs() < MATE_THRESHOLD {
+            alpha = previous_score - delta;
+            beta = previous_score + delta;
+        }
+        loop {
+            let score = self.negamax(bs, depth, 0, alpha, beta, None);
+            if self.is_stopped() {
+                return score;
+            }
+            if score <= alpha {
+                alpha = (alpha - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (beta + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+            delta *= 2;
+            if delta > 1000 {
+                alpha = -INFINITY;
+                beta = INFINITY;
+            }
+        }
+    }
+
+    fn negamax(&mut self, bs: &BoardState, depth: i32, ply: i32, mut alpha: i32, beta: i32, previous_move: Option<Move>) -> i32 {
+        self.pv_table[ply as usize].clear();
+        if self.should_stop() {
+            return 0;
+        }
+        let in_check = bs.is_in_check();
+        let mut depth = depth;
+        // a check is searched one ply deeper so forcing sequences are seen to their end
+        if in_check && self.options.check_extensions && ply < MAX_PLY as i32 / 2 {
+            depth += 1;
+        }
+        if depth <= 0 || ply >= MAX_PLY as i32 - 1 {
+            return self.quiescence(bs, ply, alpha, beta);
+        }
+        self.total_nodes.fetch_add(1, Ordering::Relaxed);
+        let key = bs.zobrist_key();
+        if ply > 0 && self.is_draw(bs, key) {
+            return 0;
+        }
+
+        let is_pv_node = beta - alpha > 1;
+        let mut hash_move = None;
+        if let Some(hit) = self.transposition_table.probe(key, ply) {
+            hash_move = hit.best_move;
+            // cutoffs in the principal variation would cut it short
+            if !is_pv_node && hit.depth >= depth {
+                let is_cutoff = match hit.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => hit.score >= beta,
+                    Bound::Upper => hit.score <= alpha,
+                };
+                if is_cutoff {
+                    return hit.score;
+                }
+            }
+        }
+
+        // the distance to mate is exact, so is the score
+        if ply > 0 {
+            if let Some(dtm) = self.endgame_tables.as_ref().and_then(|endgame_tables| endgame_tables.probe(bs)) {
+                let score = match dtm {
+                    Dtm::Win(plies) => MATE_SCORE - ply - plies as i32,
+                    Dtm::Loss(plies) => -MATE_SCORE + ply + plies as i32,
+                    Dtm::Draw => 0,
+                };
+                self.transposition_table.store(key, depth, Bound::Exact, score, None, ply);
+                return score;
+            }
+        }
+
+        // right after a capture or pawn move the position may have just entered the tablebases
+        if ply > 0 && bs.half_move_clock() == 0 {
+            if let Some(wdl) = self.tablebases.as_ref().and_then(|tablebases| tablebases.probe_wdl(bs)) {
+                // cursed wins and blessed losses are draws under the 50-move rule
+                let (score, bound) = match wdl {
+                    Wdl::Win => (TABLEBASE_WIN - ply, Bound::Lower),
+                    Wdl::Loss => (-TABLEBASE_WIN + ply, Bound::Upper),
+                    _ => (0, Bound::Exact),
+                };
+                let is_cutoff = match bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if is_cutoff {
+                    self.transposition_table.store(key, depth, bound, score, None, ply);
+                    return score;
+                }
+            }
+        }
+
+        let static_eval = if in_check { -INFINITY } else { self.static_evaluation(bs, ply) };
+        if !is_pv_node && !in_check && ply > 0 {
+            // the position is so good that even after giving away some margin the opponent won't allow it
+            if self.options.reverse_futility_pruning
+                && depth <= 6
+                && beta.abs() < MATE_THRESHOLD
+                && static_eval - REVERSE_FUTILITY_MARGIN * depth >= beta
+            {
+                return static_eval;
+            }
+            // the position is so bad that only captures could save it
+            if self.options.razoring && depth <= 2 && static_eval + RAZORING_MARGIN * depth <= alpha {
+                let score = self.quiescence(bs, ply, alpha, alpha + 1);
+                if score <= alpha {
+                    return score;
+                }
+            }
+            // if passing the turn still doesn't let the opponent get below beta, a real move won't either,
+            // except in zugzwang where every move makes the position worse, which is common with only pawns left
+            if self.options.null_move && depth >= 3 && static_eval >= beta && previous_move.is_some() && has_non_pawn_material(bs) {
+                let reduction = 2 + depth / 4;
+                let null_state = bs.make_null_move();
+                self.update_accumulator(bs, None, &null_state, ply + 1);
+                self.key_history.push(key);
+                let score = -self.negamax(&null_state, depth - 1 - reduction, ply + 1, -beta, -beta + 1, None);
+                self.key_history.pop();
+                if self.is_stopped() {
+                    return 0;
+                }
+                if score >= beta {
+                    // unproven mates are not returned
+                    return if score >= MATE_THRESHOLD { beta } else { score };
+                }
+            }
+        }
+
+        let mut picker = if self.options.move_ordering {
+            MovePicker::new(&self.ordering, ply as usize, hash_move, previous_move)
+        } else {
+            MovePicker::new_unordered(bs)
+        };
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut moves_searched = 0;
+        let mut tried_quiets = Vec::new();
+        self.key_history.push(key);
+        while let Some(chess_move) = picker.next(bs, &self.ordering) {
+            if ply == 0 && (self.excluded_root_moves.contains(&chess_move) || self.losing_root_moves.contains(&chess_move)) {
+                continue;
+            }
+            let new_state = bs.make_move(chess_move);
+            let is_quiet = !is_tactical(bs, chess_move);
+            let gives_check = new_state.is_in_check();
+            let can_be_pruned = is_quiet && !in_check && !gives_check && moves_searched > 0;
+            // quiet moves can't make up for a static evaluation far below alpha
+            if self.options.futility_pruning && can_be_pruned && !is_pv_node && depth <= 3 && static_eval + FUTILITY_MARGIN * depth <= alpha {
+                tried_quiets.push(chess_move);
+                continue;
+            }
+            self.update_accumulator(bs, Some(chess_move), &new_state, ply + 1);
+            self.pv_table[ply as usize + 1].clear();
+            let score;
+            if moves_searched == 0 {
+                score = -self.negamax(&new_state, depth - 1, ply + 1, -beta, -alpha, Some(chess_move));
+            } else {
+                // late moves are most likely bad, they are searched shallower and only again if they surprise
+                let reduction = if self.options.late_move_reductions && can_be_pruned && depth >= 3 && moves_searched >= 3 {
+                    late_move_reduction(depth, moves_searched)
+                } else {
+                    0
+                };
+                // after the first move every other one is expected to be worse, which a null window proves cheaply
+                let mut null_window_score = -self.negamax(&new_state, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha, Some(chess_move));
+                if null_window_score > alpha && reduction > 0 {
+                    null_window_score = -self.negamax(&new_state, depth - 1, ply + 1, -alpha - 1, -alpha, Some(chess_move));
+                }
+                if null_window_score > alpha && null_window_score < beta {
+                    score = -self.negamax(&new_state, depth - 1, ply + 1, -beta, -alpha, Some(chess_move));
+                } else {
+                    score = null_window_score;
+                }
+            }
+            moves_searched += 1;
+            if self.is_stopped() {
+                self.key_history.pop();
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(chess_move);
+            }
+            if score > alpha {
+                alpha = score;
+                if is_pv_node {
+                    let mut pv = vec![chess_move];
+                    pv.extend_from_slice(&self.pv_table[ply as usize + 1]);
+                    self.pv_table[ply as usize] = pv;
+                }
+            }
+            if alpha >= beta {
+                if is_quiet {
+                    self.ordering
+                        .update_quiet_cutoff(bs.color_to_move(), ply as usize, depth, chess_move, previous_move, &tried_quiets);
+                }
+                break;
+            }
+            if is_quiet {
+                tried_quiets.push(chess_move);
+            }
+        }
+        self.key_history.pop();
+
+        // the first move is never pruned, so there are no legal moves
+        if moves_searched == 0 {
+            return if in_check { -MATE_SCORE + ply } else { 0 };
+        }
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // with excluded moves the root result isn't the real one of the position
+        if ply > 0 || self.excluded_root_moves.is_empty() {
+            self.transposition_table.store(key, depth, bound, best_score, best_move, ply);
+        }
+        return best_score;
+    }
+
+    /// only looks at captures so the evaluation is not done in the middle of an exchange
+    fn quiescence(&mut self, bs: &BoardState, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.total_nodes.fetch_add(1, Ordering::Relaxed);
+        let stand_pat = self.static_evaluation(bs, ply);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+        let mut picker = MovePicker::new_captures(None);
+        while let Some(chess_move) = picker.next(bs, &self.ordering) {
+            let new_state = bs.make_move(chess_move);
+            self.update_accumulator(bs, Some(chess_move), &new_state, ply + 1);
+            let score = -self.quiescence(&new_state, ply + 1, -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        return alpha;
+    }
+
+    fn static_evaluation(&mut self, bs: &BoardState, ply: i32) -> i32 {
+        return match &self.network {
+            Some(network) => network.evaluate(&self.accumulators[ply as usize], bs.color_to_move()),
+            None => evaluate_with_pawn_table(bs, &mut self.pawn_table),
+        };
+    }
+
+    /// the accumulator of the ply of the new position follows from the one of the position before the move
+    fn update_accumulator(&mut self, bs: &BoardState, chess_move: Option<Move>, new_state: &BoardState, ply: i32) {
+        let Some(network) = &self.network else {
+            return;
+        };
+        // the quiescence search can go deeper than the maximum ply of the main search
+        let ply = ply as usize;
+        if ply >= self.accumulators.len() {
+            self.accumulators.push(network.new_accumulator());
+        }
+        let (parents, children) = self.accumulators.split_at_mut(ply);
+        network.update(bs, chess_move, new_state, &parents[ply - 1], &mut children[0]);
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if let Some(max_nodes) = self.max_nodes {
+            if self.total_nodes.load(Ordering::Relaxed) >= max_nodes {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+        }
+        if let Some(max_time) = self.max_time {
+            self.nodes_since_time_check += 1;
+            if self.nodes_since_time_check >= TIME_CHECK_INTERVAL {
+                self.nodes_since_time_check = 0;
+                if self.clock.elapsed().is_some_and(|elapsed| elapsed >= max_time) {
+                    self.stop.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        return self.is_stopped();
+    }
+
+    fn is_stopped(&self) -> bool {
+        return self.stop.load(Ordering::Relaxed);
+    }
+
+    fn is_draw(&self, bs: &BoardState, key: u64) -> bool {
+        if bs.half_move_clock() >= 100 {
+            return true;
+        }
+        // positions with the same side to move are two plies apart
+        return self.key_history.iter().rev().skip(1).step_by(2).any(|k| *k == key);
+    }
+}
+
+fn late_move_reduction(depth: i32, moves_searched: i32) -> i32 {
+    let reduction = 0.75 + (depth as f64).ln() * (moves_searched as f64).ln() / 2.25;
+    return (reduction as i32).clamp(1, depth - 2);
+}
+
+/// whether the side to move has any piece besides pawns and the king
+fn has_non_pawn_material(bs: &BoardState) -> bool {
+    for index in 0..64 {
+        let piece = bs.get_piece_at_position(Position::position_from_indices(index));
+        let colorless_piece = piece & PIECE_MASK;
+        if piece & COLOR_MASK == bs.color_to_move() && colorless_piece != PAWN && colorless_piece != KING && colorless_piece != NOTHING {
+            return true;
+        }
+    }
+    return false;
+}
@@ -0,0 +1,151 @@
+use crate::board_state::{BoardState, Position, BISHOP, KING, KNIGHT, NOTHING, PIECE_MASK, WHITE};
+use crate::search::{SearchLimits, SearchOptions, Searcher};
+
+// games that take longer are called a draw
+pub(crate) const MAX_GAME_PLIES: usize = 400;
+
+/// balanced positions after a few opening moves, every one is played with both colors
+const OPENINGS: [&str; 8] = [
+    "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+    "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+    "rnbqkbnr/pppp1ppp/4p3/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pp1ppppp/2p5/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq d6 0 2",
+    "rnbqkb1r/pppppppp/5n2/8/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 1 2",
+    "rnbqkbnr/pppppppp/8/8/2P5/8/PP1PPPPP/RNBQKBNR b KQkq c3 0 1",
+    "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 2 2",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchResult {
+    pub fn games(&self) -> u32 {
+        return self.wins + self.draws + self.losses;
+    }
+
+    /// the elo difference of the first engine to the second one and the 95% error margin
+    pub fn elo(&self) -> (f64, f64) {
+        let games = self.games() as f64;
+        if games == 0.0 {
+            return (0.0, 0.0);
+        }
+        let score = (self.wins as f64 + self.draws as f64 / 2.0) / games;
+        let variance =
+            (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) + self.losses as f64 * score.powi(2)) / games;
+        let margin = 1.96 * (variance / games).sqrt();
+        return (
+            elo_from_score(score),
+            (elo_from_score(score + margin) - elo_from_score(score - margin)) / 2.0,
+        );
+    }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    return -400.0 * (1.0 / score - 1.0).log10();
+}
+
+/// plays both colors of every opening in turn until the number of games is reached, the result is from the view of the first engine
+pub fn run_match(options: SearchOptions, opponent_options: SearchOptions, games: u32, limits: &SearchLimits) -> MatchResult {
+    let mut result = MatchResult::default();
+    for game in 0..games {
+        let opening = OPENINGS[(game / 2) as usize % OPENINGS.len()];
+        let engine_plays_white = game % 2 == 0;
+        let mut engine = Searcher::new();
+        engine.options = options;
+        let mut opponent = Searcher::new();
+        opponent.options = opponent_options;
+        let game_result = if engine_plays_white {
+            play_game(&BoardState::from_fen(opening), &mut engine, &mut opponent, limits).0
+        } else {
+            play_game(&BoardState::from_fen(opening), &mut opponent, &mut engine, limits).0
+        };
+        match (game_result, engine_plays_white) {
+            (GameResult::Draw, _) => result.draws += 1,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => result.wins += 1,
+            _ => result.losses += 1,
+        }
+        let (elo, margin) = result.elo();
+        println!(
+            "game {}/{}: +{} ={} -{}  elo {:.0} +/- {:.0}",
+            game + 1,
+            games,
+            result.wins,
+            result.draws,
+            result.losses,
+            elo,
+            margin
+        );
+    }
+    return result;
+}
+
+/// plays a game between two searchers and returns the result and every position of the game
+pub fn play_game(start: &BoardState, white: &mut Searcher, black: &mut Searcher, limits: &SearchLimits) -> (GameResult, Vec<BoardState>) {
+    let mut bs = start.clone();
+    let mut positions = vec![bs.clone()];
+    let mut keys: Vec<u64> = Vec::new();
+    loop {
+        if let Some(result) = game_result(&bs, &keys) {
+            return (result, positions);
+        }
+        if positions.len() > MAX_GAME_PLIES {
+            return (GameResult::Draw, positions);
+        }
+        let searcher = if bs.color_to_move() == WHITE { &mut *white } else { &mut *black };
+        searcher.set_game_history(keys.clone());
+        let best_move = searcher
+            .search(&bs, limits)
+            .best_move
+            .expect("a position that isn't over has a legal move");
+        keys.push(bs.zobrist_key());
+        bs = bs.make_move(best_move);
+        positions.push(bs.clone());
+    }
+}
+
+/// the result if the game is over, the keys are those of the earlier positions of the game
+pub fn game_result(bs: &BoardState, keys: &[u64]) -> Option<GameResult> {
+    if bs.get_legal_moves().is_empty() {
+        if !bs.is_in_check() {
+            return Some(GameResult::Draw);
+        }
+        return Some(if bs.color_to_move() == WHITE {
+            GameResult::BlackWins
+        } else {
+            GameResult::WhiteWins
+        });
+    }
+    let key = bs.zobrist_key();
+    let repetitions = keys.iter().filter(|k| **k == key).count();
+    if bs.half_move_clock() >= 100 || repetitions >= 2 || is_insufficient_material(bs) {
+        return Some(GameResult::Draw);
+    }
+    return None;
+}
+
+/// only kings and at most one minor piece are left, so nobody can be mated
+fn is_insufficient_material(bs: &BoardState) -> bool {
+    let mut minor_pieces = 0;
+    for index in 0..64 {
+        let piece = bs.get_piece_at_position(Position::position_from_indices(index)) & PIECE_MASK;
+        match piece {
+            NOTHING | KING => {}
+            KNIGHT | BISHOP => minor_pieces += 1,
+            _ => return false,
+        }
+    }
+    return minor_pieces <= 1;
+}
@@ -0,0 +1,968 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::board_state::{BoardState, Move, Position, BISHOP, BLACK, KING, KNIGHT, NOTHING, PAWN, PIECE_MASK, QUEEN, ROOK, WHITE};
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+const WDL_SUFFIX: &str = ".rtbw";
+const DTZ_SUFFIX: &str = ".rtbz";
+
+// flags of a table part
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// the material in table names, strongest piece first
+const PIECE_LETTERS: [(char, i32); 6] = [('K', KING), ('Q', QUEEN), ('R', ROOK), ('B', BISHOP), ('N', KNIGHT), ('P', PAWN)];
+// root moves are ranked by these, wins within the 50-move rule above wins that come too late and so on
+const RANK_STEP: i32 = 1 << 18;
+
+/// the result with best play from the view of the side to move, cursed wins and blessed losses are wins and losses
+/// that the 50-move rule turns into draws
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        return match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        };
+    }
+
+    /// -2 for a loss up to 2 for a win
+    pub fn value(&self) -> i32 {
+        return *self as i32 - 2;
+    }
+
+    /// the same result from the view of the opponent
+    pub fn flipped(&self) -> Wdl {
+        return Wdl::from_value(-self.value());
+    }
+}
+
+/// the WDL and DTZ tables of a directory, tables are read when they are first needed
+pub struct Tablebases {
+    // the file of every table by its name like "KQvK", without the suffix
+    wdl_files: HashMap<String, TableFile>,
+    dtz_files: HashMap<String, TableFile>,
+    max_pieces: usize,
+}
+
+/// a table file, read by the first probe that needs it without locking the ones after it
+struct TableFile {
+    path: PathBuf,
+    // None for tables that couldn't be read, so they are only tried once
+    table: OnceLock<Option<Table>>,
+}
+
+impl TableFile {
+    fn new(path: PathBuf) -> TableFile {
+        return TableFile {
+            path,
+            table: OnceLock::new(),
+        };
+    }
+}
+
+impl Tablebases {
+    /// the directories are separated like in PATH, ':' or ';' on Windows
+    pub fn open(path: &str) -> Result<Tablebases, String> {
+        let mut tablebases = Tablebases {
+            wdl_files: HashMap::new(),
+            dtz_files: HashMap::new(),
+            max_pieces: 0,
+        };
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        for directory in path.split(separator).filter(|directory| !directory.is_empty()) {
+            let entries = fs::read_dir(directory).map_err(|error| format!("Could not read {}: {}", directory, error))?;
+            for entry in entries.flatten() {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if let Some(name) = file_name.strip_suffix(WDL_SUFFIX).filter(|name| is_table_name(name)) {
+                    tablebases.max_pieces = tablebases.max_pieces.max(name.len() - 1);
+                    tablebases.wdl_files.insert(name.to_string(), TableFile::new(entry.path()));
+                } else if let Some(name) = file_name.strip_suffix(DTZ_SUFFIX).filter(|name| is_table_name(name)) {
+                    tablebases.dtz_files.insert(name.to_string(), TableFile::new(entry.path()));
+                }
+            }
+        }
+        if tablebases.wdl_files.is_empty() {
+            return Err(format!("No Syzygy tables found in {}", path));
+        }
+        return Ok(tablebases);
+    }
+
+    /// the most pieces, kings included, of any table
+    pub fn max_pieces(&self) -> usize {
+        return self.max_pieces;
+    }
+
+    pub fn table_count(&self) -> usize {
+        return self.wdl_files.len() + self.dtz_files.len();
+    }
+
+    /// None if the position has castling rights or one of the tables needed is missing
+    pub fn probe_wdl(&self, bs: &BoardState) -> Option<Wdl> {
+        if !self.covers(bs) {
+            return None;
+        }
+        return self.search_wdl(bs, false).map(|(wdl, _)| wdl);
+    }
+
+    /// the distance to the next capture or pawn move (which resets the 50-move counter) in plies with best play,
+    /// positive when the side to move wins, negative when it loses, 0 for draws, off by one ply in rare cases
+    pub fn probe_dtz(&self, bs: &BoardState) -> Option<i32> {
+        if !self.covers(bs) {
+            return None;
+        }
+        return self.search_dtz(bs);
+    }
+
+    /// the legal moves that keep the best result the tables promise, of the winning moves only the ones that reach
+    /// the next zeroing move the fastest so the win is always converted
+    pub fn root_moves(&self, bs: &BoardState) -> Option<Vec<Move>> {
+        if !self.covers(bs) {
+            return None;
+        }
+        let clock = bs.half_move_clock() as i32;
+        let mut ranked_moves = Vec::new();
+        for chess_move in bs.get_legal_moves() {
+            let new_state = bs.make_move(chess_move);
+            let mut dtz = if new_state.half_move_clock() == 0 {
+                dtz_before_zeroing(self.search_wdl(&new_state, false)?.0.flipped())
+            } else {
+                let dtz = -self.search_dtz(&new_state)?;
+                dtz + dtz.signum()
+            };
+            if dtz == 2 && new_state.is_checkmate() {
+                dtz = 1;
+            }
+            let rank = if dtz > 0 && dtz + clock <= 100 {
+                2 * RANK_STEP - dtz
+            } else if dtz > 0 {
+                RANK_STEP - dtz - clock
+            } else if dtz < 0 && -dtz + clock > 100 {
+                -RANK_STEP - dtz
+            } else if dtz < 0 {
+                -2 * RANK_STEP - dtz
+            } else {
+                0
+            };
+            ranked_moves.push((chess_move, rank));
+        }
+        let best_rank = ranked_moves.iter().map(|(_, rank)| *rank).max()?;
+        return Some(
+            ranked_moves
+                .into_iter()
+                .filter(|(_, rank)| *rank == best_rank)
+                .map(|(chess_move, _)| chess_move)
+                .collect(),
+        );
+    }
+
+    /// castling isn't in the tables, positions with too many pieces or without a table aren't probed at all
+    fn covers(&self, bs: &BoardState) -> bool {
+        if bs.has_castling_rights() || piece_count(bs) > self.max_pieces {
+            return false;
+        }
+        // there is no table for the bare kings, it's always a draw
+        if piece_count(bs) == 2 {
+            return true;
+        }
+        let (white, black) = (material(bs, WHITE), material(bs, BLACK));
+        return self.wdl_files.contains_key(&format!("{}v{}", white, black)) || self.wdl_files.contains_key(&format!("{}v{}", black, white));
+    }
+
+    /// the tables don't know en passant and hold "don't care" values where a capture is the best move, so captures are
+    /// searched first, the second value tells whether the best move is one that resets the 50-move counter
+    fn search_wdl(&self, bs: &BoardState, include_pawn_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = bs.get_legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for chess_move in &moves {
+            let is_pawn_move = bs.get_piece_at_position(chess_move.from) & PIECE_MASK == PAWN;
+            let is_zeroing = bs.is_capture(*chess_move) || (include_pawn_moves && is_pawn_move);
+            if !is_zeroing {
+                continue;
+            }
+            searched += 1;
+            let wdl = self.search_wdl(&bs.make_move(*chess_move), false)?.0.flipped();
+            if wdl > best {
+                best = wdl;
+                if wdl == Wdl::Win {
+                    return Some((wdl, true));
+                }
+            }
+        }
+        // with every move searched the table isn't needed, it could even be wrong with en passant
+        let all_searched = searched > 0 && searched == moves.len();
+        let wdl = if all_searched {
+            best
+        } else {
+            Wdl::from_value(self.probe_table(bs, false, Wdl::Draw)?.0)
+        };
+        if best >= wdl {
+            return Some((best, best > Wdl::Draw || all_searched));
+        }
+        return Some((wdl, false));
+    }
+
+    fn search_dtz(&self, bs: &BoardState) -> Option<i32> {
+        let (wdl, zeroing_is_best) = self.search_wdl(bs, true)?;
+        // draws aren't in the DTZ tables
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_is_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+        let (dtz, stored) = self.probe_table(bs, true, wdl)?;
+        if stored {
+            let cursed = if wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss { 100 } else { 0 };
+            return Some((dtz + cursed) * wdl.value().signum());
+        }
+        // the table only has the other side to move, the best move is the one with the best DTZ after it
+        let mut best_dtz = i32::MAX;
+        for chess_move in bs.get_legal_moves() {
+            let zeroing = bs.is_capture(chess_move) || bs.get_piece_at_position(chess_move.from) & PIECE_MASK == PAWN;
+            let new_state = bs.make_move(chess_move);
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search_wdl(&new_state, false)?.0)
+            } else {
+                -self.search_dtz(&new_state)?
+            };
+            if dtz == 1 && new_state.is_checkmate() {
+                best_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < best_dtz && dtz.signum() == wdl.value().signum() {
+                best_dtz = dtz;
+            }
+        }
+        // without legal moves the side to move is mated
+        return Some(if best_dtz == i32::MAX { -1 } else { best_dtz });
+    }
+
+    /// the stored value of the position and whether it was stored at all, DTZ tables only have one side to move
+    fn probe_table(&self, bs: &BoardState, dtz: bool, wdl: Wdl) -> Option<(i32, bool)> {
+        if piece_count(bs) == 2 {
+            return Some((0, true));
+        }
+        let (white, black) = (material(bs, WHITE), material(bs, BLACK));
+        let name = format!("{}v{}", white, black);
+        // the tables have the stronger side as white, otherwise the colors are swapped
+        let (name, black_stronger) = if self.wdl_files.contains_key(&name) {
+            (name, false)
+        } else {
+            (format!("{}v{}", black, white), true)
+        };
+        let table = self.table(&name, dtz)?;
+        // symmetric tables only have white to move
+        let flip = black_stronger || (table.symmetric && bs.color_to_move() == BLACK);
+        return table.probe(bs, flip, wdl);
+    }
+
+    fn table(&self, name: &str, dtz: bool) -> Option<&Table> {
+        let files = if dtz { &self.dtz_files } else { &self.wdl_files };
+        let file = files.get(name)?;
+        return file.table.get_or_init(|| Table::load(name, &file.path, dtz).ok()).as_ref();
+    }
+}
+
+fn is_table_name(name: &str) -> bool {
+    let Some((white, black)) = name.split_once('v') else {
+        return false;
+    };
+    return [white, black]
+        .iter()
+        .all(|side| side.starts_with('K') && side.chars().all(|letter| "KQRBNP".contains(letter)));
+}
+
+fn piece_count(bs: &BoardState) -> usize {
+    return (0..64)
+        .filter(|index| bs.get_piece_at_position(Position::position_from_indices(*index)) != NOTHING)
+        .count();
+}
+
+/// the pieces of one color as in the table names, like "KRP"
+fn material(bs: &BoardState, color: i32) -> String {
+    let mut material = String::new();
+    for (letter, piece) in PIECE_LETTERS {
+        for index in 0..64 {
+            if bs.get_piece_at_position(Position::position_from_indices(index)) == piece | color {
+                material.push(letter);
+            }
+        }
+    }
+    return material;
+}
+
+/// the DTZ of a position whose best move resets the 50-move counter
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    return match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    };
+}
+
+/// the pieces as the tables number them, pawn to king are 1 to 6 and black pieces have 8 added
+fn table_piece(piece: i32) -> u8 {
+    let kind = match piece & PIECE_MASK {
+        PAWN => 1,
+        KNIGHT => 2,
+        BISHOP => 3,
+        ROOK => 4,
+        QUEEN => 5,
+        _ => 6,
+    };
+    return if piece & BLACK != 0 { kind + 8 } else { kind };
+}
+
+fn rank_of(square: usize) -> usize {
+    return square / 8;
+}
+
+fn file_of(square: usize) -> usize {
+    return square % 8;
+}
+
+/// negative below the a1-h8 diagonal, 0 on it and positive above it
+fn off_diagonal(square: usize) -> i32 {
+    return rank_of(square) as i32 - file_of(square) as i32;
+}
+
+/// the tables that turn the squares of the pieces into the index of the position
+struct Encoding {
+    binomial: [[u64; 64]; 7],
+    // squares a2-h7 numbered so that the leading pawn, nearest to the edge and lowest, has the highest number
+    map_pawns: [usize; 64],
+    lead_pawn_index: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+    // the b1-h1-h7 triangle below the diagonal
+    map_b1h1h7: [usize; 64],
+    // the a1-d1-d4 triangle, the squares on the diagonal last
+    map_a1d1d4: [usize; 64],
+    // the 462 legal placements of two kings with the first one in the a1-d1-d4 triangle
+    map_kk: [[u64; 64]; 10],
+}
+
+fn encoding() -> &'static Encoding {
+    static ENCODING: OnceLock<Encoding> = OnceLock::new();
+    return ENCODING.get_or_init(|| {
+        let mut encoding = Encoding {
+            binomial: [[0; 64]; 7],
+            map_pawns: [0; 64],
+            lead_pawn_index: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                encoding.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+        let mut code = 0;
+        let mut diagonal = Vec::new();
+        for square in 0..28 {
+            if off_diagonal(square) < 0 && file_of(square) <= 3 {
+                encoding.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 && file_of(square) <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            encoding.map_a1d1d4[square] = code;
+            code += 1;
+        }
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for index in 0..10 {
+            // squares outside of the triangle are mapped to 0 as well, b1 is the real 0
+            for first in (0..28).filter(|square| encoding.map_a1d1d4[*square] == index && (index > 0 || *square == 1)) {
+                for second in 0..64 {
+                    let touching = rank_of(first).abs_diff(rank_of(second)) <= 1 && file_of(first).abs_diff(file_of(second)) <= 1;
+                    if touching || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((index, second));
+                    } else {
+                        encoding.map_kk[index][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (index, second) in both_on_diagonal {
+            encoding.map_kk[index][second] = code;
+            code += 1;
+        }
+        for n in 0..64 {
+            for k in 0..7 {
+                encoding.binomial[k][n] = match (k, n) {
+                    (0, _) => 1,
+                    (_, 0) => 0,
+                    _ => encoding.binomial[k - 1][n - 1] + encoding.binomial[k][n - 1],
+                };
+            }
+        }
+        let mut available_squares = 47;
+        for lead_pawns in 1..6 {
+            for file in 0..4 {
+                let mut index = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        encoding.map_pawns[square] = available_squares;
+                        encoding.map_pawns[square ^ 7] = available_squares - 1;
+                        available_squares = available_squares.saturating_sub(2);
+                    }
+                    encoding.lead_pawn_index[lead_pawns][square] = index;
+                    index += encoding.binomial[lead_pawns - 1][encoding.map_pawns[square]];
+                }
+                encoding.lead_pawns_size[lead_pawns][file] = index;
+            }
+        }
+        return encoding;
+    });
+}
+
+/// one part of a table, a table has one per side to move and for tables with pawns one per file of the leading pawn
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    // the order of the pieces in the index
+    pieces: Vec<u8>,
+    // pieces of the same kind form a group, the leading group is encoded with the triangle tables
+    group_len: Vec<usize>,
+    // the factor of every group in the index, the last one is the size of the table
+    group_index: Vec<u64>,
+    block_size: u64,
+    span: u64,
+    sparse_index_size: u64,
+    block_length_size: u64,
+    block_count: u64,
+    // the value itself in single value parts
+    min_symbol_length: u8,
+    base64: Vec<u64>,
+    symbol_length: Vec<u8>,
+    // offsets into the file
+    lowest_symbol: usize,
+    symbol_tree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    dtz_map_index: [usize; 4],
+}
+
+/// a WDL or DTZ table, the values are compressed with recursive pairing and a canonical Huffman code
+struct Table {
+    bytes: Vec<u8>,
+    dtz: bool,
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // pawns of the leading color first
+    pawn_count: [usize; 2],
+    // by side to move and file of the leading pawn
+    parts: Vec<Vec<PairsData>>,
+    dtz_map: usize,
+}
+
+impl Table {
+    fn load(name: &str, path: &Path, dtz: bool) -> Result<Table, String> {
+        let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if bytes.len() < 6 || bytes[0..4] != magic {
+            return Err(format!("{} is not a Syzygy table", path.display()));
+        }
+        let (white, black) = name.split_once('v').ok_or(format!("Not a table name: {}", name))?;
+        let white_pawns = white.matches('P').count();
+        let black_pawns = black.matches('P').count();
+        // with pawns on both sides the side with fewer of them leads
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let mut table = Table {
+            bytes,
+            dtz,
+            symmetric: white == black,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: [white, black]
+                .iter()
+                .any(|side| "QRBNP".chars().any(|letter| side.matches(letter).count() == 1)),
+            pawn_count: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            parts: Vec::new(),
+            dtz_map: 0,
+        };
+        table.read_parts(name.len() - 1)?;
+        return Ok(table);
+    }
+
+    fn read_parts(&mut self, piece_count: usize) -> Result<(), String> {
+        let sides = if !self.dtz && !self.symmetric { 2 } else { 1 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let both_have_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut parts = vec![vec![PairsData::default(); files]; sides];
+        // the first byte holds flags
+        let mut offset = 5;
+        for file in 0..files {
+            let first = self.byte(offset);
+            let second = if both_have_pawns { self.byte(offset + 1) } else { 0xFF };
+            let orders = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            offset += 1 + both_have_pawns as usize;
+            for _ in 0..piece_count {
+                for (side, side_parts) in parts.iter_mut().enumerate() {
+                    let piece = if side == 1 { self.byte(offset) >> 4 } else { self.byte(offset) & 0xF };
+                    side_parts[file].pieces.push(piece);
+                }
+                offset += 1;
+            }
+            for (side, side_parts) in parts.iter_mut().enumerate() {
+                self.set_groups(&mut side_parts[file], orders[side], file);
+            }
+        }
+        offset += offset & 1;
+        for file in 0..files {
+            for side_parts in parts.iter_mut() {
+                offset = self.read_sizes(&mut side_parts[file], offset)?;
+            }
+        }
+        if self.dtz {
+            self.dtz_map = offset;
+            for part in parts[0].iter_mut().filter(|part| part.flags & FLAG_MAPPED != 0) {
+                for map_index in part.dtz_map_index.iter_mut() {
+                    if part.flags & FLAG_WIDE != 0 {
+                        offset += offset & 1;
+                        *map_index = (offset - self.dtz_map) / 2 + 1;
+                        offset += 2 * self.u16_le(offset) as usize + 2;
+                    } else {
+                        *map_index = offset - self.dtz_map + 1;
+                        offset += self.byte(offset) as usize + 1;
+                    }
+                }
+            }
+            offset += offset & 1;
+        }
+        for file in 0..files {
+            for side_parts in parts.iter_mut() {
+                side_parts[file].sparse_index = offset;
+                offset += side_parts[file].sparse_index_size as usize * 6;
+            }
+        }
+        for file in 0..files {
+            for side_parts in parts.iter_mut() {
+                side_parts[file].block_length = offset;
+                offset += side_parts[file].block_length_size as usize * 2;
+            }
+        }
+        for file in 0..files {
+            for side_parts in parts.iter_mut() {
+                offset = (offset + 0x3F) & !0x3F;
+                side_parts[file].data = offset;
+                offset += (side_parts[file].block_count * side_parts[file].block_size) as usize;
+            }
+        }
+        if offset > self.bytes.len() {
+            return Err(String::from("The table is shorter than its header says"));
+        }
+        self.parts = parts;
+        return Ok(());
+    }
+
+    /// splits the pieces into groups and works out the factor of every group in the index
+    fn set_groups(&self, part: &mut PairsData, order: [u8; 2], file: usize) {
+        let encoding = encoding();
+        let mut first_len: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let mut group_len = vec![1];
+        for i in 1..part.pieces.len() {
+            first_len -= 1;
+            if first_len > 0 || part.pieces[i] == part.pieces[i - 1] {
+                *group_len.last_mut().expect("there is a first group") += 1;
+            } else {
+                group_len.push(1);
+            }
+        }
+        let groups = group_len.len();
+        let both_have_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if both_have_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - group_len[0] - if both_have_pawns { group_len[1] } else { 0 };
+        let mut group_index = vec![0; groups + 1];
+        let mut index: u64 = 1;
+        let mut k = 0;
+        while next < groups || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                group_index[0] = index;
+                index *= if self.has_pawns {
+                    encoding.lead_pawns_size[group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                group_index[1] = index;
+                index *= encoding.binomial[group_len[1]][48 - group_len[0]];
+            } else {
+                group_index[next] = index;
+                index *= encoding.binomial[group_len[next]][free_squares];
+                free_squares -= group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        group_index[groups] = index;
+        part.group_len = group_len;
+        part.group_index = group_index;
+    }
+
+    /// the block sizes and the Huffman code of a part
+    fn read_sizes(&self, part: &mut PairsData, mut offset: usize) -> Result<usize, String> {
+        part.flags = self.byte(offset);
+        offset += 1;
+        if part.flags & FLAG_SINGLE_VALUE != 0 {
+            part.min_symbol_length = self.byte(offset);
+            return Ok(offset + 1);
+        }
+        let table_size = *part.group_index.last().expect("the size is the last group index");
+        if self.byte(offset) > 32 || self.byte(offset + 1) > 32 {
+            return Err(String::from("Invalid block size"));
+        }
+        part.block_size = 1 << self.byte(offset);
+        part.span = 1 << self.byte(offset + 1);
+        part.sparse_index_size = table_size.div_ceil(part.span);
+        let padding = self.byte(offset + 2) as u64;
+        part.block_count = self.u32_le(offset + 3) as u64;
+        part.block_length_size = part.block_count + padding;
+        let max_symbol_length = self.byte(offset + 7);
+        part.min_symbol_length = self.byte(offset + 8);
+        offset += 9;
+        if max_symbol_length < part.min_symbol_length || part.min_symbol_length == 0 || max_symbol_length > 64 {
+            return Err(String::from("Invalid symbol lengths"));
+        }
+        part.lowest_symbol = offset;
+        // the canonical code has the longest symbols first, base64 holds the lowest code of every length padded to 64 bits
+        let lengths = (max_symbol_length - part.min_symbol_length + 1) as usize;
+        part.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = self.u16_le(part.lowest_symbol + 2 * i) as u64;
+            let next_lowest = self.u16_le(part.lowest_symbol + 2 * i + 2) as u64;
+            part.base64[i] = (part.base64[i + 1] + lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in part.base64.iter_mut().enumerate() {
+            let shift = 64 - i as u32 - part.min_symbol_length as u32;
+            *base = base.checked_shl(shift).unwrap_or(0);
+        }
+        offset += lengths * 2;
+        let symbol_count = self.u16_le(offset) as usize;
+        offset += 2;
+        part.symbol_tree = offset;
+        part.symbol_length = vec![0; symbol_count];
+        let mut visited = vec![false; symbol_count];
+        for symbol in 0..symbol_count {
+            if !visited[symbol] {
+                self.set_symbol_length(part, symbol, &mut visited);
+            }
+        }
+        return Ok(offset + symbol_count * 3 + (symbol_count & 1));
+    }
+
+    /// every symbol stands for a pair of symbols or a value, the length is the number of values it stands for minus 1
+    fn set_symbol_length(&self, part: &mut PairsData, symbol: usize, visited: &mut [bool]) {
+        visited[symbol] = true;
+        let (left, right) = self.symbol_pair(part, symbol);
+        if right == 0xFFF || left >= visited.len() || right >= visited.len() {
+            return;
+        }
+        for child in [left, right] {
+            if !visited[child] {
+                self.set_symbol_length(part, child, visited);
+            }
+        }
+        part.symbol_length[symbol] = part.symbol_length[left].wrapping_add(part.symbol_length[right]).wrapping_add(1);
+    }
+
+    /// twelve bits each, for symbols that stand for a value the left one is the value
+    fn symbol_pair(&self, part: &PairsData, symbol: usize) -> (usize, usize) {
+        let offset = part.symbol_tree + 3 * symbol;
+        let (first, second, third) = (self.byte(offset) as usize, self.byte(offset + 1) as usize, self.byte(offset + 2) as usize);
+        return ((second & 0xF) << 8 | first, third << 4 | second >> 4);
+    }
+
+    /// the value with the given index
+    fn decompress(&self, part: &PairsData, index: u64) -> i32 {
+        if part.flags & FLAG_SINGLE_VALUE != 0 {
+            return part.min_symbol_length as i32;
+        }
+        // the sparse index points to the block of every span-th value, from there the blocks are walked
+        let k = (index / part.span) as usize;
+        let mut block = self.u32_le(part.sparse_index + 6 * k) as usize;
+        let mut offset = self.u16_le(part.sparse_index + 6 * k + 4) as i64;
+        offset += (index % part.span) as i64 - (part.span / 2) as i64;
+        while offset < 0 && block > 0 {
+            block -= 1;
+            offset += self.u16_le(part.block_length + 2 * block) as i64 + 1;
+        }
+        while offset > self.u16_le(part.block_length + 2 * block) as i64 {
+            offset -= self.u16_le(part.block_length + 2 * block) as i64 + 1;
+            block += 1;
+        }
+        let mut position = part.data + block * part.block_size as usize;
+        let mut buffer = self.u64_be(position);
+        position += 8;
+        let mut buffer_size = 64;
+        let min_length = part.min_symbol_length as usize;
+        let mut symbol;
+        loop {
+            let mut length = 0;
+            while length + 1 < part.base64.len() && buffer < part.base64[length] {
+                length += 1;
+            }
+            symbol = (buffer.wrapping_sub(part.base64[length]) >> (64 - length - min_length)) as usize;
+            symbol += self.u16_le(part.lowest_symbol + 2 * length) as usize;
+            let Some(symbol_length) = part.symbol_length.get(symbol) else {
+                return 0;
+            };
+            if offset < *symbol_length as i64 + 1 {
+                break;
+            }
+            offset -= *symbol_length as i64 + 1;
+            length += min_length;
+            buffer = buffer.checked_shl(length as u32).unwrap_or(0);
+            buffer_size -= length;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (self.u32_be(position) as u64) << (64 - buffer_size);
+                position += 4;
+            }
+        }
+        // the symbol stands for a sequence of values, the pairs are followed down to the one with the offset
+        while part.symbol_length[symbol] > 0 {
+            let (left, right) = self.symbol_pair(part, symbol);
+            // only a broken file has symbols outside of the tree
+            let Some(left_length) = part.symbol_length.get(left) else {
+                return 0;
+            };
+            if right >= part.symbol_length.len() {
+                return 0;
+            }
+            if offset < *left_length as i64 + 1 {
+                symbol = left;
+            } else {
+                offset -= *left_length as i64 + 1;
+                symbol = right;
+            }
+        }
+        return self.symbol_pair(part, symbol).0 as i32;
+    }
+
+    /// the value of the position, the colors are swapped when flip is set, None if only the other side to move is stored
+    fn probe(&self, bs: &BoardState, flip: bool, wdl: Wdl) -> Option<(i32, bool)> {
+        let encoding = encoding();
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side = (flip != (bs.color_to_move() == BLACK)) as usize;
+        let mut board = Vec::new();
+        for index in 0..64 {
+            let piece = bs.get_piece_at_position(Position::position_from_indices(index));
+            if piece != NOTHING {
+                board.push((index ^ flip_squares, table_piece(piece) ^ flip_color));
+            }
+        }
+        let mut squares = Vec::new();
+        let mut pieces = Vec::new();
+        let mut file = 0;
+        let mut lead_pawns = 0;
+        // the pawns of the leading color come first, the one with the highest number leads
+        let lead_pawn = if self.has_pawns { Some(self.parts[0][0].pieces[0]) } else { None };
+        if let Some(lead_pawn) = lead_pawn {
+            for (square, piece) in board.iter().filter(|(_, piece)| *piece == lead_pawn) {
+                squares.push(*square);
+                pieces.push(*piece);
+            }
+            lead_pawns = squares.len();
+            let mut leading = 0;
+            for i in 1..lead_pawns {
+                if encoding.map_pawns[squares[i]] > encoding.map_pawns[squares[leading]] {
+                    leading = i;
+                }
+            }
+            squares.swap(0, leading);
+            file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+        // symmetric tables without pawns are the same for both sides to move
+        let both_sides_stored = self.symmetric && !self.has_pawns;
+        if self.dtz && (self.parts[0][file].flags & FLAG_STM) as usize != side && !both_sides_stored {
+            return Some((0, false));
+        }
+        for (square, piece) in board.iter().filter(|(_, piece)| Some(*piece) != lead_pawn) {
+            squares.push(*square);
+            pieces.push(*piece);
+        }
+        let part = &self.parts[side % self.parts.len()][file];
+        if pieces.len() != part.pieces.len() || (self.has_pawns && lead_pawns == 0) {
+            return None;
+        }
+        // the pieces in the order of the table
+        for i in lead_pawns..pieces.len().saturating_sub(1) {
+            for j in i + 1..pieces.len() {
+                if part.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+        // the leading piece is mirrored to the a-d files
+        if file_of(squares[0]) > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 7;
+            }
+        }
+        let mut index;
+        if self.has_pawns {
+            index = encoding.lead_pawn_index[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|square| encoding.map_pawns[*square]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                index += encoding.binomial[i][encoding.map_pawns[*square]];
+            }
+        } else {
+            // and without pawns to the first four ranks and below the diagonal
+            if rank_of(squares[0]) > 3 {
+                for square in squares.iter_mut() {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..part.group_len[0] {
+                if off_diagonal(squares[i]) == 0 {
+                    continue;
+                }
+                if off_diagonal(squares[i]) > 0 {
+                    for square in squares[i..].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            index = if self.has_unique_pieces {
+                unique_pieces_index(encoding, squares[0], squares[1], squares[2])
+            } else {
+                encoding.map_kk[encoding.map_a1d1d4[squares[0]]][squares[1]]
+            };
+        }
+        index *= part.group_index[0];
+        // the other groups by their squares, squares taken by earlier groups don't count
+        let mut group_start = part.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        for next in 1..part.group_len.len() {
+            let group_end = group_start + part.group_len[next];
+            squares[group_start..group_end].sort();
+            let mut group = 0;
+            for i in 0..part.group_len[next] {
+                let square = squares[group_start + i];
+                let taken_below = squares[..group_start].iter().filter(|taken| square > **taken).count();
+                let first_rank = if remaining_pawns { 8 } else { 0 };
+                group += encoding.binomial[i + 1][square - taken_below - first_rank];
+            }
+            remaining_pawns = false;
+            index += group * part.group_index[next];
+            group_start = group_end;
+        }
+        let value = self.decompress(part, index);
+        if !self.dtz {
+            return Some((value - 2, true));
+        }
+        return Some((self.map_dtz(file, value, wdl), true));
+    }
+
+    /// DTZ tables can store their values through a map and in moves instead of plies
+    fn map_dtz(&self, file: usize, value: i32, wdl: Wdl) -> i32 {
+        let part = &self.parts[0][file];
+        let mut value = value;
+        if part.flags & FLAG_MAPPED != 0 {
+            let map_index = part.dtz_map_index[[1, 3, 0, 2, 0][wdl as usize]] + value as usize;
+            value = if part.flags & FLAG_WIDE != 0 {
+                self.u16_le(self.dtz_map + 2 * map_index) as i32
+            } else {
+                self.byte(self.dtz_map + map_index) as i32
+            };
+        }
+        let in_moves = match wdl {
+            Wdl::Win => part.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => part.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        return if in_moves { value * 2 + 1 } else { value + 1 };
+    }
+
+    fn byte(&self, offset: usize) -> u8 {
+        return self.bytes.get(offset).copied().unwrap_or(0);
+    }
+
+    fn u16_le(&self, offset: usize) -> u16 {
+        return u16::from_le_bytes([self.byte(offset), self.byte(offset + 1)]);
+    }
+
+    fn u32_le(&self, offset: usize) -> u32 {
+        return u32::from_le_bytes([self.byte(offset), self.byte(offset + 1), self.byte(offset + 2), self.byte(offset + 3)]);
+    }
+
+    fn u32_be(&self, offset: usize) -> u32 {
+        return u32::from_be_bytes([self.byte(offset), self.byte(offset + 1), self.byte(offset + 2), self.byte(offset + 3)]);
+    }
+
+    fn u64_be(&self, offset: usize) -> u64 {
+        return (self.u32_be(offset) as u64) << 32 | self.u32_be(offset + 4) as u64;
+    }
+}
+
+/// three different pieces lead, the first one in the a1-d1-d4 triangle, the 31332 placements are counted by how many
+/// of them are on the diagonal
+fn unique_pieces_index(encoding: &Encoding, first: usize, second: usize, third: usize) -> u64 {
+    let adjust_second = (second > first) as usize;
+    let adjust_third = (third > first) as usize + (third > second) as usize;
+    let index = if off_diagonal(first) != 0 {
+        (encoding.map_a1d1d4[first] * 63 + second - adjust_second) * 62 + third - adjust_third
+    } else if off_diagonal(second) != 0 {
+        (6 * 63 + rank_of(first) * 28 + encoding.map_b1h1h7[second]) * 62 + third - adjust_third
+    } else if off_diagonal(third) != 0 {
+        6 * 63 * 62 + 4 * 28 * 62 + rank_of(first) * 7 * 28 + (rank_of(second) - adjust_second) * 28 + encoding.map_b1h1h7[third]
+    } else {
+        6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank_of(first) * 7 * 6 + (rank_of(second) - adjust_second) * 6 + rank_of(third) - adjust_third
+    };
+    return index as u64;
+}
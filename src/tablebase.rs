@@ -0,0 +1,892 @@
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::board_state::{
+    BoardState, Move, Position, BISHOP, BLACK, COLOR_MASK, KING, KNIGHT, NOTHING, PAWN, PIECE_MASK, PIECE_VALUES, QUEEN, ROOK, WHITE,
+};
+
+/// kings included, with more pieces the tables get too big to hold in memory while they are generated
+pub const MAX_PIECES: usize = 4;
+pub const FILE_SUFFIX: &str = ".ctb";
+
+// the file starts with the magic bytes, the version and the name of the ending (length first), followed by the values
+// of all positions as runs: the value and the length of the run as a LEB128 number
+const MAGIC: &[u8; 4] = b"CTTB";
+const VERSION: u8 = 1;
+
+// the material in table names, strongest piece first
+const PIECE_LETTERS: [(char, i32); 6] = [('K', KING), ('Q', QUEEN), ('R', ROOK), ('B', BISHOP), ('N', KNIGHT), ('P', PAWN)];
+const PROMOTIONS: [i32; 4] = [QUEEN, ROOK, BISHOP, KNIGHT];
+// every position is turned so the white king stands in the a1-d1-d4 triangle, with pawns only mirrored to the a-d files
+const TRIANGLE: [usize; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+// the values are stored in a byte, longer mates than this aren't possible with four pieces
+const MAX_PLIES: usize = 254;
+
+const KING_STEPS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const KNIGHT_STEPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+/// the distance to mate with best play from the view of the side to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dtm {
+    /// the side to move mates in this many plies
+    Win(u32),
+    /// the side to move is mated in this many plies, 0 when it already is
+    Loss(u32),
+    Draw,
+}
+
+impl Dtm {
+    /// wins take an odd number of plies and losses an even one, so plies + 1 fits both in one byte with 0 for draws
+    fn from_byte(byte: u8) -> Dtm {
+        return match byte {
+            0 => Dtm::Draw,
+            _ if byte % 2 == 1 => Dtm::Loss(byte as u32 - 1),
+            _ => Dtm::Win(byte as u32 - 1),
+        };
+    }
+
+    fn to_byte(self) -> u8 {
+        return match self {
+            Dtm::Win(plies) | Dtm::Loss(plies) => plies as u8 + 1,
+            Dtm::Draw => 0,
+        };
+    }
+
+    /// the value of the position before the move that led here, for the side that made the move
+    pub fn before_move(self) -> Dtm {
+        return match self {
+            Dtm::Win(plies) => Dtm::Loss(plies + 1),
+            Dtm::Loss(plies) => Dtm::Win(plies + 1),
+            Dtm::Draw => Dtm::Draw,
+        };
+    }
+
+    /// higher is better for the side to move, faster wins and slower losses first
+    pub fn rank(self) -> i32 {
+        return match self {
+            Dtm::Win(plies) => 1000 - plies as i32,
+            Dtm::Loss(plies) => -1000 + plies as i32,
+            Dtm::Draw => 0,
+        };
+    }
+}
+
+/// the distance to mate of every position of one ending, like KQvK
+pub struct Table {
+    name: String,
+    // the white king first, then the other white pieces and the black ones strongest first
+    pieces: Vec<i32>,
+    has_pawns: bool,
+    positions_per_side: usize,
+    values: Vec<u8>,
+}
+
+impl Table {
+    fn new(pieces: Vec<i32>) -> Table {
+        let has_pawns = pieces.iter().any(|piece| piece & PIECE_MASK == PAWN);
+        let king_squares = if has_pawns { 32 } else { TRIANGLE.len() };
+        let positions_per_side = king_squares * 64usize.pow(pieces.len() as u32 - 1);
+        return Table {
+            name: material_name(&pieces),
+            pieces,
+            has_pawns,
+            positions_per_side,
+            values: vec![0; 2 * positions_per_side],
+        };
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// the longest mate the side to move can force in plies
+    pub fn longest_mate(&self) -> u32 {
+        return self
+            .values
+            .iter()
+            .filter_map(|value| {
+                if let Dtm::Win(plies) = Dtm::from_byte(*value) {
+                    Some(plies)
+                } else {
+                    None
+                }
+            })
+            .max()
+            .unwrap_or(0);
+    }
+
+    fn symmetries(&self) -> usize {
+        return if self.has_pawns { 2 } else { 8 };
+    }
+
+    /// the lowest index of all the mirror images of the position that have the white king in its region, so every
+    /// position has exactly one index
+    fn index(&self, placement: &Placement) -> usize {
+        let mut best = usize::MAX;
+        for symmetry in 0..self.symmetries() {
+            let Some(mut index) = king_region_index(transform(placement.squares[0], symmetry), self.has_pawns) else {
+                continue;
+            };
+            for square in &placement.squares[1..placement.count] {
+                index = index * 64 + transform(*square, symmetry);
+            }
+            best = best.min(index);
+        }
+        return if placement.white_to_move { best } else { best + self.positions_per_side };
+    }
+
+    fn placement(&self, index: usize) -> Placement {
+        let mut placement = Placement {
+            pieces: [NOTHING; MAX_PIECES],
+            squares: [0; MAX_PIECES],
+            count: self.pieces.len(),
+            white_to_move: index < self.positions_per_side,
+        };
+        placement.pieces[..self.pieces.len()].copy_from_slice(&self.pieces);
+        let mut rest = index % self.positions_per_side;
+        for square in placement.squares[1..placement.count].iter_mut().rev() {
+            *square = rest % 64;
+            rest /= 64;
+        }
+        placement.squares[0] = if self.has_pawns { (rest / 4) * 8 + rest % 4 } else { TRIANGLE[rest] };
+        return placement;
+    }
+
+    fn value(&self, placement: &Placement) -> Dtm {
+        return Dtm::from_byte(self.values[self.index(placement)]);
+    }
+
+    /// writes the table as <directory>/<name>.ctb
+    pub fn save(&self, directory: &str) -> Result<(), String> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(self.name.len() as u8);
+        bytes.extend(self.name.as_bytes());
+        let mut start = 0;
+        while start < self.values.len() {
+            let value = self.values[start];
+            let length = self.values[start..].iter().take_while(|other| **other == value).count();
+            bytes.push(value);
+            let mut rest = length;
+            while rest >= 128 {
+                bytes.push((rest & 127) as u8 | 128);
+                rest >>= 7;
+            }
+            bytes.push(rest as u8);
+            start += length;
+        }
+        let path = Path::new(directory).join(format!("{}{}", self.name, FILE_SUFFIX));
+        return fs::write(&path, bytes).map_err(|error| format!("Could not write {}: {}", path.display(), error));
+    }
+
+    pub fn load(path: &Path) -> Result<Table, String> {
+        let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+        let invalid = || format!("{} is not a chesstacean tablebase", path.display());
+        if bytes.len() < 6 || &bytes[0..4] != MAGIC || bytes[4] != VERSION {
+            return Err(invalid());
+        }
+        let name_end = 6 + bytes[5] as usize;
+        let name = String::from_utf8(bytes.get(6..name_end).ok_or_else(invalid)?.to_vec()).map_err(|_| invalid())?;
+        let mut table = Table::new(parse_name(&name)?);
+        let mut position = name_end;
+        let mut start = 0;
+        while position < bytes.len() {
+            let value = bytes[position];
+            position += 1;
+            let mut length = 0;
+            let mut shift = 0;
+            loop {
+                let byte = *bytes.get(position).ok_or_else(invalid)?;
+                position += 1;
+                length |= ((byte & 127) as usize) << shift;
+                shift += 7;
+                if byte < 128 || shift > 35 {
+                    break;
+                }
+            }
+            let run = table.values.get_mut(start..start + length).ok_or_else(invalid)?;
+            run.fill(value);
+            start += length;
+        }
+        if start != table.values.len() {
+            return Err(invalid());
+        }
+        return Ok(table);
+    }
+}
+
+/// all the tables of a directory
+pub struct EndgameTables {
+    tables: HashMap<String, Table>,
+}
+
+impl EndgameTables {
+    /// reads every .ctb file of the directory
+    pub fn load(directory: &str) -> Result<EndgameTables, String> {
+        let mut tables = HashMap::new();
+        let entries = fs::read_dir(directory).map_err(|error| format!("Could not read {}: {}", directory, error))?;
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().ends_with(FILE_SUFFIX) {
+                let table = Table::load(&entry.path())?;
+                tables.insert(table.name.clone(), table);
+            }
+        }
+        if tables.is_empty() {
+            return Err(format!("No tablebases found in {}", directory));
+        }
+        return Ok(EndgameTables { tables });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.tables.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.tables.is_empty();
+    }
+
+    /// None for positions with more pieces, castling rights or without a table
+    pub fn probe(&self, bs: &BoardState) -> Option<Dtm> {
+        let placement = Placement::from_board(bs)?;
+        // en passant isn't in the tables, when it's possible the moves are looked at one by one
+        let both_have_pawns = [WHITE, BLACK]
+            .iter()
+            .all(|color| placement.pieces[..placement.count].contains(&(PAWN | color)));
+        if both_have_pawns && bs.get_legal_moves().iter().any(|chess_move| is_en_passant(bs, *chess_move)) {
+            return self.best_moves(bs).map(|(_, dtm)| dtm);
+        }
+        return probe(&self.tables, &placement);
+    }
+
+    /// the moves with the best distance to mate and that distance
+    pub fn best_moves(&self, bs: &BoardState) -> Option<(Vec<Move>, Dtm)> {
+        Placement::from_board(bs)?;
+        let mut best: Option<(Vec<Move>, Dtm)> = None;
+        for chess_move in bs.get_legal_moves() {
+            let dtm = self.probe(&bs.make_move(chess_move))?.before_move();
+            match &mut best {
+                Some((moves, best_dtm)) if dtm == *best_dtm => moves.push(chess_move),
+                Some((_, best_dtm)) if dtm.rank() <= best_dtm.rank() => {}
+                _ => best = Some((vec![chess_move], dtm)),
+            }
+        }
+        if best.is_none() {
+            // no legal moves, mate or stalemate
+            return Some((Vec::new(), if bs.is_in_check() { Dtm::Loss(0) } else { Dtm::Draw }));
+        }
+        return best;
+    }
+}
+
+fn is_en_passant(bs: &BoardState, chess_move: Move) -> bool {
+    let is_pawn = bs.get_piece_at_position(chess_move.from) & PIECE_MASK == PAWN;
+    return is_pawn && chess_move.from.col != chess_move.to.col && bs.get_piece_at_position(chess_move.to) == NOTHING;
+}
+
+/// the positions after every legal move as the generator finds them, None for positions that aren't in any table,
+/// the generator has its own move generation for speed and get_legal_moves is what it has to agree with
+pub fn successors(bs: &BoardState) -> Option<Vec<BoardState>> {
+    let placement = Placement::from_board(bs)?;
+    return Some(placement.successors().iter().map(|(successor, _)| successor.board_state()).collect());
+}
+
+/// the positions of the same material the generator finds the position can be reached from
+pub fn predecessors(bs: &BoardState) -> Option<Vec<BoardState>> {
+    let placement = Placement::from_board(bs)?;
+    return Some(placement.predecessors().iter().map(|predecessor| predecessor.board_state()).collect());
+}
+
+/// called after each table is built, the smaller endings a table needs come before it
+pub type ProgressCallback = Box<dyn FnMut(&GeneratedTable)>;
+
+/// what building one table found and how long it took
+pub struct GeneratedTable {
+    pub name: String,
+    /// the legal positions with either side to move
+    pub positions: usize,
+    /// in plies
+    pub longest_mate: u32,
+    pub time: Duration,
+}
+
+/// builds tables by retrograde analysis, the endings a capture or promotion leads to are built first, en passant is
+/// left out so KPvKP positions where it's possible are probed one move deeper
+#[derive(Default)]
+pub struct Generator {
+    tables: HashMap<String, Table>,
+    progress_callback: Option<ProgressCallback>,
+}
+
+impl Generator {
+    pub fn new() -> Generator {
+        return Generator {
+            tables: HashMap::new(),
+            progress_callback: None,
+        };
+    }
+
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
+
+    /// the table of the ending like "KBNvK" and of all smaller endings it needs
+    pub fn generate(&mut self, name: &str) -> Result<&Table, String> {
+        let pieces = normalized_pieces(&parse_name(name)?);
+        let name = material_name(&pieces);
+        if pieces.len() < 3 {
+            return Err(format!("{} is always a draw", name));
+        }
+        self.build(pieces);
+        return Ok(&self.tables[&name]);
+    }
+
+    /// the generated tables sorted by name
+    pub fn tables(&self) -> Vec<&Table> {
+        let mut tables: Vec<&Table> = self.tables.values().collect();
+        tables.sort_by_key(|table| (table.pieces.len(), table.name.clone()));
+        return tables;
+    }
+
+    fn build(&mut self, pieces: Vec<i32>) {
+        for smaller in smaller_endings(&pieces) {
+            if smaller.len() > 2 && !self.tables.contains_key(&material_name(&smaller)) {
+                self.build(smaller);
+            }
+        }
+        let start = Instant::now();
+        let mut table = Table::new(pieces);
+        let size = table.values.len();
+        // positions are final once their value is known, the others are draws at the end
+        let mut state = vec![UNKNOWN; size];
+        // the moves that stay in the table whose result isn't known to lose yet
+        let mut remaining = vec![0u8; size];
+        let mut can_draw = vec![false; size];
+        let mut has_winning_exit = vec![false; size];
+        // the longest loss through a capture or promotion
+        let mut losing_exit = vec![0u8; size];
+        // positions by the number of plies they are won or lost in, each one is final the first time it comes up
+        let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); MAX_PLIES + 1];
+        for index in 0..size {
+            let placement = table.placement(index);
+            if !placement.is_valid() || table.index(&placement) != index {
+                state[index] = ILLEGAL;
+                continue;
+            }
+            let successors = placement.successors();
+            if successors.is_empty() {
+                if placement.in_check(placement.side_to_move()) {
+                    buckets[0].push(index as u32);
+                } else {
+                    state[index] = FINAL;
+                }
+                continue;
+            }
+            let mut children = Vec::new();
+            let mut fastest_win = usize::MAX;
+            for (successor, changes_material) in successors {
+                if !changes_material {
+                    children.push(table.index(&successor));
+                    continue;
+                }
+                match probe(&self.tables, &successor).unwrap_or(Dtm::Draw).before_move() {
+                    Dtm::Win(plies) => fastest_win = fastest_win.min(plies as usize),
+                    Dtm::Loss(plies) => losing_exit[index] = losing_exit[index].max(plies as u8),
+                    Dtm::Draw => can_draw[index] = true,
+                }
+            }
+            children.sort_unstable();
+            children.dedup();
+            remaining[index] = children.len() as u8;
+            if fastest_win <= MAX_PLIES {
+                has_winning_exit[index] = true;
+                buckets[fastest_win].push(index as u32);
+            } else if children.is_empty() && !can_draw[index] {
+                buckets[losing_exit[index] as usize].push(index as u32);
+            }
+        }
+        for plies in 0..=MAX_PLIES {
+            for index in mem::take(&mut buckets[plies]) {
+                let index = index as usize;
+                if state[index] != UNKNOWN {
+                    continue;
+                }
+                state[index] = FINAL;
+                let is_loss = plies % 2 == 0;
+                let dtm = if is_loss { Dtm::Loss(plies as u32) } else { Dtm::Win(plies as u32) };
+                table.values[index] = dtm.to_byte();
+                let mut parents: Vec<usize> = table.placement(index).predecessors().iter().map(|parent| table.index(parent)).collect();
+                parents.sort_unstable();
+                parents.dedup();
+                for parent in parents {
+                    if state[parent] != UNKNOWN || plies == MAX_PLIES {
+                        continue;
+                    }
+                    if is_loss {
+                        // the parent can move into a lost position
+                        buckets[plies + 1].push(parent as u32);
+                        continue;
+                    }
+                    remaining[parent] -= 1;
+                    // every move loses, the longest loss counts
+                    if remaining[parent] == 0 && !can_draw[parent] && !has_winning_exit[parent] {
+                        buckets[(plies + 1).max(losing_exit[parent] as usize)].push(parent as u32);
+                    }
+                }
+            }
+        }
+        if let Some(callback) = self.progress_callback.as_mut() {
+            callback(&GeneratedTable {
+                name: table.name.clone(),
+                positions: state.iter().filter(|state| **state != ILLEGAL).count(),
+                longest_mate: table.longest_mate(),
+                time: start.elapsed(),
+            });
+        }
+        self.tables.insert(table.name.clone(), table);
+    }
+}
+
+// the states of positions while a table is built
+const UNKNOWN: u8 = 0;
+const FINAL: u8 = 1;
+const ILLEGAL: u8 = 2;
+
+/// the value from the table of the position's material, two kings are a draw
+fn probe(tables: &HashMap<String, Table>, placement: &Placement) -> Option<Dtm> {
+    if placement.count == 2 {
+        return Some(Dtm::Draw);
+    }
+    let placement = placement.normalized();
+    let table = tables.get(&material_name(&placement.pieces[..placement.count]))?;
+    return Some(table.value(&placement));
+}
+
+/// the pieces of a name like "KQvK", white's first
+fn parse_name(name: &str) -> Result<Vec<i32>, String> {
+    let invalid = || format!("Not an ending: {}, it should look like KQvK", name);
+    let (white, black) = name.split_once('v').ok_or_else(invalid)?;
+    let mut pieces = Vec::new();
+    for (side, color) in [(white, WHITE), (black, BLACK)] {
+        if side.matches('K').count() != 1 {
+            return Err(invalid());
+        }
+        for letter in side.chars() {
+            let (_, piece) = PIECE_LETTERS
+                .iter()
+                .find(|(piece_letter, _)| *piece_letter == letter)
+                .ok_or_else(invalid)?;
+            pieces.push(piece | color);
+        }
+    }
+    if pieces.len() > MAX_PIECES {
+        return Err(format!("Only endings with up to {} pieces are supported", MAX_PIECES));
+    }
+    pieces.sort_by_key(|piece| piece_order(*piece));
+    return Ok(pieces);
+}
+
+fn material_name(pieces: &[i32]) -> String {
+    let side = |color: i32| -> String {
+        return PIECE_LETTERS
+            .iter()
+            .flat_map(|(letter, piece)| pieces.iter().filter(move |other| **other == piece | color).map(move |_| *letter))
+            .collect();
+    };
+    return format!("{}v{}", side(WHITE), side(BLACK));
+}
+
+/// white's pieces first, each side from the king to the pawns
+fn piece_order(piece: i32) -> (bool, usize) {
+    let kind = PIECE_LETTERS.iter().position(|(_, kind)| *kind == piece & PIECE_MASK).unwrap_or(0);
+    return (piece & COLOR_MASK == BLACK, kind);
+}
+
+/// the tables have the stronger side as white
+fn black_is_stronger(pieces: &[i32]) -> bool {
+    let strength = |color: i32| -> (i32, String) {
+        let value = pieces
+            .iter()
+            .filter(|piece| *piece & COLOR_MASK == color && *piece & PIECE_MASK != KING)
+            .map(|piece| PIECE_VALUES[(piece & PIECE_MASK) as usize])
+            .sum();
+        let letters = material_name(pieces).split('v').nth((color == BLACK) as usize).unwrap_or("").to_string();
+        return (value, letters);
+    };
+    return strength(BLACK) > strength(WHITE);
+}
+
+fn normalized_pieces(pieces: &[i32]) -> Vec<i32> {
+    let flip = black_is_stronger(pieces);
+    let mut pieces: Vec<i32> = pieces.iter().map(|piece| if flip { swap_color(*piece) } else { *piece }).collect();
+    pieces.sort_by_key(|piece| piece_order(*piece));
+    return pieces;
+}
+
+/// the endings after one capture, promotion or capture with promotion
+fn smaller_endings(pieces: &[i32]) -> Vec<Vec<i32>> {
+    let mut endings = Vec::new();
+    let without = |index: usize, pieces: &[i32]| -> Vec<i32> {
+        pieces
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, piece)| *piece)
+            .collect()
+    };
+    for (index, piece) in pieces.iter().enumerate() {
+        if piece & PIECE_MASK != KING {
+            endings.push(without(index, pieces));
+        }
+        if piece & PIECE_MASK != PAWN {
+            continue;
+        }
+        for promotion in PROMOTIONS {
+            let mut promoted = pieces.to_vec();
+            promoted[index] = promotion | (piece & COLOR_MASK);
+            for (captured, other) in pieces.iter().enumerate() {
+                if other & COLOR_MASK != piece & COLOR_MASK && other & PIECE_MASK != KING {
+                    endings.push(without(captured, &promoted));
+                }
+            }
+            endings.push(promoted);
+        }
+    }
+    return endings.iter().map(|ending| normalized_pieces(ending)).collect();
+}
+
+fn swap_color(piece: i32) -> i32 {
+    return (piece & PIECE_MASK) | if piece & COLOR_MASK == WHITE { BLACK } else { WHITE };
+}
+
+/// the index of the white king's square in the region the symmetries move it to, None outside of it
+fn king_region_index(square: usize, has_pawns: bool) -> Option<usize> {
+    if has_pawns {
+        return if square % 8 < 4 { Some(square / 8 * 4 + square % 8) } else { None };
+    }
+    return TRIANGLE.iter().position(|triangle_square| *triangle_square == square);
+}
+
+/// one of the eight symmetries of the board: mirroring the files, the ranks and along the diagonal
+fn transform(square: usize, symmetry: usize) -> usize {
+    let (mut file, mut rank) = (square % 8, square / 8);
+    if symmetry & 4 != 0 {
+        mem::swap(&mut file, &mut rank);
+    }
+    if symmetry & 1 != 0 {
+        file = 7 - file;
+    }
+    if symmetry & 2 != 0 {
+        rank = 7 - rank;
+    }
+    return rank * 8 + file;
+}
+
+fn step(square: usize, (file_step, rank_step): (i32, i32)) -> Option<usize> {
+    let file = (square % 8) as i32 + file_step;
+    let rank = (square / 8) as i32 + rank_step;
+    if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+        return None;
+    }
+    return Some((rank * 8 + file) as usize);
+}
+
+/// a position of a few pieces, much faster to work with than a BoardState
+#[derive(Clone, Copy)]
+struct Placement {
+    pieces: [i32; MAX_PIECES],
+    squares: [usize; MAX_PIECES],
+    count: usize,
+    white_to_move: bool,
+}
+
+impl Placement {
+    fn from_board(bs: &BoardState) -> Option<Placement> {
+        if bs.has_castling_rights() {
+            return None;
+        }
+        let mut placement = Placement {
+            pieces: [NOTHING; MAX_PIECES],
+            squares: [0; MAX_PIECES],
+            count: 0,
+            white_to_move: bs.color_to_move() == WHITE,
+        };
+        for square in 0..64 {
+            let piece = bs.get_piece_at_position(Position::position_from_indices(square));
+            if piece == NOTHING {
+                continue;
+            }
+            if placement.count == MAX_PIECES {
+                return None;
+            }
+            placement.pieces[placement.count] = piece;
+            placement.squares[placement.count] = square;
+            placement.count += 1;
+        }
+        return Some(placement);
+    }
+
+    fn side_to_move(&self) -> i32 {
+        return if self.white_to_move { WHITE } else { BLACK };
+    }
+
+    /// with the colors swapped if black is stronger and the pieces in the order of the table
+    fn normalized(&self) -> Placement {
+        let mut placement = *self;
+        if black_is_stronger(&self.pieces[..self.count]) {
+            for i in 0..placement.count {
+                placement.pieces[i] = swap_color(placement.pieces[i]);
+                placement.squares[i] ^= 56;
+            }
+            placement.white_to_move = !placement.white_to_move;
+        }
+        let mut order: Vec<usize> = (0..placement.count).collect();
+        order.sort_by_key(|i| piece_order(placement.pieces[*i]));
+        let unordered = placement;
+        for (slot, i) in order.into_iter().enumerate() {
+            placement.pieces[slot] = unordered.pieces[i];
+            placement.squares[slot] = unordered.squares[i];
+        }
+        return placement;
+    }
+
+    fn board(&self) -> [i32; 64] {
+        let mut board = [NOTHING; 64];
+        for i in 0..self.count {
+            board[self.squares[i]] = self.pieces[i];
+        }
+        return board;
+    }
+
+    /// without castling rights or an en passant square
+    fn board_state(&self) -> BoardState {
+        let board = self.board();
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for piece in &board[rank * 8..rank * 8 + 8] {
+                if *piece == NOTHING {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    fen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                let (letter, _) = PIECE_LETTERS.iter().find(|(_, kind)| *kind == piece & PIECE_MASK).expect("a known piece");
+                fen.push(if piece & COLOR_MASK == WHITE {
+                    *letter
+                } else {
+                    letter.to_ascii_lowercase()
+                });
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+        fen.push_str(if self.white_to_move { " w - - 0 1" } else { " b - - 0 1" });
+        return BoardState::from_fen(&fen);
+    }
+
+    /// no two pieces on a square, no pawns on the first or last rank and the side that just moved isn't in check
+    fn is_valid(&self) -> bool {
+        for i in 0..self.count {
+            if (i + 1..self.count).any(|j| self.squares[j] == self.squares[i]) {
+                return false;
+            }
+            if self.pieces[i] & PIECE_MASK == PAWN && !(8..56).contains(&self.squares[i]) {
+                return false;
+            }
+        }
+        let other = if self.white_to_move { BLACK } else { WHITE };
+        return !self.in_check(other);
+    }
+
+    fn in_check(&self, color: i32) -> bool {
+        let board = self.board();
+        let Some(king) = (0..self.count).find(|i| self.pieces[*i] == KING | color) else {
+            return false;
+        };
+        return (0..self.count).any(|i| self.pieces[i] & COLOR_MASK != color && attacks(self.pieces[i], self.squares[i], self.squares[king], &board));
+    }
+
+    /// the positions after every legal move and whether the move captured or promoted, which leaves the table
+    fn successors(&self) -> Vec<(Placement, bool)> {
+        let board = self.board();
+        let color = self.side_to_move();
+        let mut successors = Vec::new();
+        for i in (0..self.count).filter(|i| self.pieces[*i] & COLOR_MASK == color) {
+            for to in destinations(self.pieces[i], self.squares[i], &board) {
+                let mut successor = *self;
+                successor.white_to_move = !self.white_to_move;
+                successor.squares[i] = to;
+                let captured = (0..self.count).find(|j| *j != i && self.squares[*j] == to);
+                if let Some(captured) = captured {
+                    for j in captured..self.count - 1 {
+                        successor.pieces[j] = successor.pieces[j + 1];
+                        successor.squares[j] = successor.squares[j + 1];
+                    }
+                    successor.count -= 1;
+                }
+                if successor.in_check(color) {
+                    continue;
+                }
+                let moved = if captured.is_some_and(|captured| captured < i) { i - 1 } else { i };
+                if self.pieces[i] & PIECE_MASK == PAWN && !(8..56).contains(&to) {
+                    for promotion in PROMOTIONS {
+                        successor.pieces[moved] = promotion | color;
+                        successors.push((successor, true));
+                    }
+                } else {
+                    successors.push((successor, captured.is_some()));
+                }
+            }
+        }
+        return successors;
+    }
+
+    /// the positions of the same material the position can be reached from, the other side moved to get here
+    fn predecessors(&self) -> Vec<Placement> {
+        let board = self.board();
+        let mover = if self.white_to_move { BLACK } else { WHITE };
+        let mut predecessors = Vec::new();
+        for i in (0..self.count).filter(|i| self.pieces[*i] & COLOR_MASK == mover) {
+            let from_squares: Vec<usize> = if self.pieces[i] & PIECE_MASK == PAWN {
+                pawn_origins(self.squares[i], mover, &board)
+            } else {
+                destinations(self.pieces[i], self.squares[i], &board)
+                    .into_iter()
+                    .filter(|square| board[*square] == NOTHING)
+                    .collect()
+            };
+            for from in from_squares {
+                let mut predecessor = *self;
+                predecessor.white_to_move = !self.white_to_move;
+                predecessor.squares[i] = from;
+                if predecessor.is_valid() {
+                    predecessors.push(predecessor);
+                }
+            }
+        }
+        return predecessors;
+    }
+}
+
+/// the squares the piece can move to, captures of the other side's pieces included
+fn destinations(piece: i32, from: usize, board: &[i32; 64]) -> Vec<usize> {
+    let color = piece & COLOR_MASK;
+    let is_free_or_capture = |square: usize| board[square] == NOTHING || board[square] & COLOR_MASK != color;
+    let mut squares = Vec::new();
+    match piece & PIECE_MASK {
+        PAWN => {
+            let forward = if color == WHITE { 1 } else { -1 };
+            if let Some(one) = step(from, (0, forward)).filter(|square| board[*square] == NOTHING) {
+                squares.push(one);
+                let start_rank = if color == WHITE { 1 } else { 6 };
+                if let Some(two) = step(one, (0, forward)).filter(|square| board[*square] == NOTHING && from / 8 == start_rank) {
+                    squares.push(two);
+                }
+            }
+            for file_step in [-1, 1] {
+                if let Some(target) =
+                    step(from, (file_step, forward)).filter(|square| board[*square] != NOTHING && board[*square] & COLOR_MASK != color)
+                {
+                    squares.push(target);
+                }
+            }
+        }
+        KING | KNIGHT => {
+            let steps = if piece & PIECE_MASK == KING { KING_STEPS } else { KNIGHT_STEPS };
+            squares.extend(
+                steps
+                    .iter()
+                    .filter_map(|offset| step(from, *offset))
+                    .filter(|square| is_free_or_capture(*square)),
+            );
+        }
+        kind => {
+            let directions: Vec<(i32, i32)> = match kind {
+                ROOK => ROOK_DIRECTIONS.to_vec(),
+                BISHOP => BISHOP_DIRECTIONS.to_vec(),
+                _ => [ROOK_DIRECTIONS, BISHOP_DIRECTIONS].concat(),
+            };
+            for direction in directions {
+                let mut square = from;
+                while let Some(next) = step(square, direction) {
+                    if is_free_or_capture(next) {
+                        squares.push(next);
+                    }
+                    if board[next] != NOTHING {
+                        break;
+                    }
+                    square = next;
+                }
+            }
+        }
+    }
+    return squares;
+}
+
+/// where a pawn on the square came from with a move that didn't capture
+fn pawn_origins(square: usize, color: i32, board: &[i32; 64]) -> Vec<usize> {
+    let backward = if color == WHITE { -1 } else { 1 };
+    let mut origins = Vec::new();
+    let Some(one) = step(square, (0, backward)).filter(|one| board[*one] == NOTHING && (8..56).contains(one)) else {
+        return origins;
+    };
+    origins.push(one);
+    let double_step_rank = if color == WHITE { 3 } else { 4 };
+    if square / 8 == double_step_rank {
+        if let Some(two) = step(one, (0, backward)).filter(|two| board[*two] == NOTHING) {
+            origins.push(two);
+        }
+    }
+    return origins;
+}
+
+fn attacks(piece: i32, from: usize, target: usize, board: &[i32; 64]) -> bool {
+    let file_distance = (from % 8).abs_diff(target % 8);
+    let rank_distance = (from / 8).abs_diff(target / 8);
+    return match piece & PIECE_MASK {
+        PAWN => {
+            let forward = if piece & COLOR_MASK == WHITE { 1 } else { -1 };
+            file_distance == 1 && target as i32 / 8 - from as i32 / 8 == forward
+        }
+        KING => file_distance <= 1 && rank_distance <= 1 && from != target,
+        KNIGHT => file_distance * rank_distance == 2,
+        kind => {
+            let straight = file_distance == 0 || rank_distance == 0;
+            let diagonal = file_distance == rank_distance;
+            let along_line = match kind {
+                ROOK => straight,
+                BISHOP => diagonal,
+                _ => straight || diagonal,
+            };
+            if !along_line || from == target {
+                return false;
+            }
+            let direction = (
+                ((target % 8) as i32 - (from % 8) as i32).signum(),
+                ((target / 8) as i32 - (from / 8) as i32).signum(),
+            );
+            let mut square = from;
+            while let Some(next) = step(square, direction) {
+                if next == target {
+                    return true;
+                }
+                if board[next] != NOTHING {
+                    return false;
+                }
+                square = next;
+            }
+            false
+        }
+    };
+}
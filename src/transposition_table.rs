@@ -0,0 +1,247 @@
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::board_state::{Move, Position};
+use crate::search::MATE_THRESHOLD;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    /// the score is exact, it lies between alpha and beta
+    Exact,
+    /// the search failed high, the real score is at least this high
+    Lower,
+    /// the search failed low, the real score is at most this high
+    Upper,
+}
+
+/// what the table knows about a position, the score is already adjusted to the ply it was probed at
+#[derive(Clone, Copy, Debug)]
+pub struct TableHit {
+    pub depth: i32,
+    pub bound: Bound,
+    pub score: i32,
+    pub best_move: Option<Move>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Entry {
+    best_move: u16,
+    score: i16,
+    depth: u8,
+    bound: u8,
+    generation: u8,
+}
+
+impl Entry {
+    fn pack(&self) -> u64 {
+        return self.best_move as u64
+            | (self.score as u16 as u64) << 16
+            | (self.depth as u64) << 32
+            | (self.bound as u64) << 40
+            | (self.generation as u64) << 48;
+    }
+
+    fn unpack(data: u64) -> Entry {
+        return Entry {
+            best_move: data as u16,
+            score: (data >> 16) as u16 as i16,
+            depth: (data >> 32) as u8,
+            bound: (data >> 40) as u8,
+            generation: (data >> 48) as u8,
+        };
+    }
+}
+
+/// the key is stored xored with the data, when two threads write the same slot at once
+/// the key doesn't match anymore and the broken entry is ignored instead of being used
+#[derive(Default)]
+struct Slot {
+    checked_key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    /// the entry if it belongs to the key, an empty entry has no bound
+    fn read(&self, key: u64) -> Option<Entry> {
+        let data = self.data.load(Ordering::Relaxed);
+        if data == 0 || self.checked_key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        return Some(Entry::unpack(data));
+    }
+
+    fn write(&self, key: u64, entry: Entry) {
+        let data = entry.pack();
+        self.checked_key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.checked_key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+// every bucket holds a depth preferred entry and an entry that is always replaced
+const BUCKET_SIZE: usize = 2;
+
+/// shared by all search threads without locking
+pub struct TranspositionTable {
+    buckets: Vec<[Slot; BUCKET_SIZE]>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let bucket_count = (megabytes * 1024 * 1024 / size_of::<[Slot; BUCKET_SIZE]>()).max(1);
+        let mut buckets = Vec::with_capacity(bucket_count);
+        buckets.resize_with(bucket_count, Default::default);
+        return TranspositionTable {
+            buckets,
+            generation: AtomicU8::new(0),
+        };
+    }
+
+    pub fn clear(&self) {
+        for bucket in &self.buckets {
+            for slot in bucket {
+                slot.clear();
+            }
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// has to be called before every search so entries of older searches get replaced first
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn probe(&self, key: u64, ply: i32) -> Option<TableHit> {
+        for slot in &self.buckets[self.bucket_index(key)] {
+            if let Some(entry) = slot.read(key) {
+                return Some(TableHit {
+                    depth: entry.depth as i32,
+                    bound: bound_from_u8(entry.bound),
+                    score: score_from_table(entry.score as i32, ply),
+                    best_move: unpack_move(entry.best_move),
+                });
+            }
+        }
+        return None;
+    }
+
+    pub fn store(&self, key: u64, depth: i32, bound: Bound, score: i32, best_move: Option<Move>, ply: i32) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let bucket = &self.buckets[self.bucket_index(key)];
+        let mut new_entry = Entry {
+            best_move: pack_move(best_move),
+            score: score_to_table(score, ply) as i16,
+            depth: depth.clamp(0, u8::MAX as i32) as u8,
+            bound: bound_to_u8(bound),
+            generation,
+        };
+        // the position is already stored, update it in place
+        for slot in bucket {
+            if let Some(entry) = slot.read(key) {
+                if new_entry.best_move == 0 {
+                    new_entry.best_move = entry.best_move;
+                }
+                slot.write(key, new_entry);
+                return;
+            }
+        }
+        // deeper searches and entries from the current search are kept in the first slot
+        let preferred_data = bucket[0].data.load(Ordering::Relaxed);
+        let preferred = Entry::unpack(preferred_data);
+        if preferred.bound == 0 || preferred.generation != generation || new_entry.depth >= preferred.depth {
+            let preferred_key = bucket[0].checked_key.load(Ordering::Relaxed) ^ preferred_data;
+            if preferred.bound != 0 {
+                bucket[1].write(preferred_key, preferred);
+            }
+            bucket[0].write(key, new_entry);
+        } else {
+            bucket[1].write(key, new_entry);
+        }
+    }
+
+    /// permille of the first entries used by the current search, the hashfull of the UCI info lines
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let sample = self.buckets.len().min(1000);
+        let mut used = 0;
+        for bucket in &self.buckets[..sample] {
+            for slot in bucket {
+                let entry = Entry::unpack(slot.data.load(Ordering::Relaxed));
+                if entry.bound != 0 && entry.generation == generation {
+                    used += 1;
+                }
+            }
+        }
+        return used * 1000 / (sample * BUCKET_SIZE);
+    }
+
+    fn bucket_index(&self, key: u64) -> usize {
+        return (key % self.buckets.len() as u64) as usize;
+    }
+}
+
+/// mate scores are stored relative to the position instead of the root, so they stay valid in transpositions
+fn score_to_table(score: i32, ply: i32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        return score + ply;
+    }
+    if score <= -MATE_THRESHOLD {
+        return score - ply;
+    }
+    return score;
+}
+
+fn score_from_table(score: i32, ply: i32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        return score - ply;
+    }
+    if score <= -MATE_THRESHOLD {
+        return score + ply;
+    }
+    return score;
+}
+
+// 0 marks an empty entry
+fn bound_to_u8(bound: Bound) -> u8 {
+    return match bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+}
+
+fn bound_from_u8(bound: u8) -> Bound {
+    return match bound {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+}
+
+// 6 bits origin, 6 bits target and 3 bits promotion, a move never has the same origin and target so 0 means no move
+fn pack_move(chess_move: Option<Move>) -> u16 {
+    return match chess_move {
+        None => 0,
+        Some(m) => {
+            let from = Position::index_from_position(m.from) as u16;
+            let to = Position::index_from_position(m.to) as u16;
+            from | to << 6 | (m.promotion as u16) << 12
+        }
+    };
+}
+
+fn unpack_move(packed: u16) -> Option<Move> {
+    if packed == 0 {
+        return None;
+    }
+    return Some(Move {
+        from: Position::position_from_indices((packed & 63) as usize),
+        to: Position::position_from_indices((packed >> 6 & 63) as usize),
+        promotion: (packed >> 12) as i32,
+    });
+}
@@ -0,0 +1,187 @@
+use std::fs;
+
+use crate::board_state::BoardState;
+use crate::eval_parameters::DEFAULT_PARAMETERS;
+use crate::evaluation::{trace_with_parameters, EvalParameters, Score};
+
+pub const DEFAULT_PASSES: usize = 100;
+
+/// a quiet position and the result of the game it was taken from, 1 for a white win, 0.5 for a draw, 0 for a black win
+pub struct TuningPosition {
+    pub bs: BoardState,
+    pub result: f64,
+}
+
+/// Texel's method: change every weight by one in both directions and keep whatever brings the static evaluation
+/// closer to the game results, until nothing improves anymore. The weights are written as Rust source after every
+/// pass, the file replaces src/eval_parameters.rs. Returns the mean error before and after tuning
+pub fn tune(positions_path: &str, output_path: &str, passes: usize) -> Result<(f64, f64), String> {
+    let positions = load_positions(positions_path)?;
+    if positions.is_empty() {
+        return Err(format!("No labelled positions in {}", positions_path));
+    }
+    let mut parameters = DEFAULT_PARAMETERS.clone();
+    let scaling = find_scaling(&positions, &parameters);
+    let start_error = mean_error(&positions, &parameters, scaling);
+    let mut best_error = start_error;
+    println!("{} positions, scaling {:.3}, error {:.6}", positions.len(), scaling, best_error);
+    let parameter_count = parameters.values_mut().len();
+    for pass in 1..=passes {
+        let mut improved = 0;
+        for index in 0..parameter_count {
+            for step in [1, -2] {
+                *parameters.values_mut()[index] += step;
+                let error = mean_error(&positions, &parameters, scaling);
+                if error < best_error {
+                    best_error = error;
+                    improved += 1;
+                    break;
+                }
+                if step == -2 {
+                    *parameters.values_mut()[index] += 1;
+                }
+            }
+        }
+        println!("pass {} changed {} weights, error {:.6}", pass, improved, best_error);
+        fs::write(output_path, rust_source(&parameters)).map_err(|error| format!("Could not write {}: {}", output_path, error))?;
+        if improved == 0 {
+            break;
+        }
+    }
+    fs::write(output_path, rust_source(&parameters)).map_err(|error| format!("Could not write {}: {}", output_path, error))?;
+    println!("tuned weights written to {}", output_path);
+    return Ok((start_error, best_error));
+}
+
+/// one position per line, a FEN followed by the result as "1-0", "0-1" or "1/2-1/2", or as a number in brackets
+pub fn load_positions(path: &str) -> Result<Vec<TuningPosition>, String> {
+    let content = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let mut positions = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_position(line) {
+            Some(position) => positions.push(position),
+            None => println!("skipping line {}: {}", line_number + 1, line),
+        }
+    }
+    return Ok(positions);
+}
+
+fn parse_position(line: &str) -> Option<TuningPosition> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 5 {
+        return None;
+    }
+    let mut result = None;
+    for field in &fields[4..] {
+        let field = field.trim_matches(|c| c == '"' || c == ';' || c == '[' || c == ']');
+        result = match field {
+            "1-0" => Some(1.0),
+            "0-1" => Some(0.0),
+            "1/2-1/2" => Some(0.5),
+            _ => field
+                .parse::<f64>()
+                .ok()
+                .filter(|value| (0.0..=1.0).contains(value) && field.contains('.')),
+        };
+        if result.is_some() {
+            break;
+        }
+    }
+    // the clocks don't matter for the evaluation and are often left out
+    let fen = format!("{} {} {} {} 0 1", fields[0], fields[1], fields[2], fields[3]);
+    return Some(TuningPosition {
+        bs: BoardState::from_fen(&fen),
+        result: result?,
+    });
+}
+
+/// the expected result for white, the scaling maps centipawns to winning chances
+fn expected_result(score: i32, scaling: f64) -> f64 {
+    return 1.0 / (1.0 + 10f64.powf(-scaling * score as f64 / 400.0));
+}
+
+fn mean_error(positions: &[TuningPosition], parameters: &EvalParameters, scaling: f64) -> f64 {
+    let mut error = 0.0;
+    for position in positions {
+        let score = trace_with_parameters(&position.bs, parameters).total();
+        error += (position.result - expected_result(score, scaling)).powi(2);
+    }
+    return error / positions.len() as f64;
+}
+
+/// the scaling that fits the current weights best, it stays fixed while tuning so only the weights move
+fn find_scaling(positions: &[TuningPosition], parameters: &EvalParameters) -> f64 {
+    let mut best_scaling = 1.0;
+    let mut step = 0.5;
+    while step >= 0.001 {
+        let mut best_error = mean_error(positions, parameters, best_scaling);
+        for candidate in [best_scaling - step, best_scaling + step] {
+            if candidate <= 0.0 {
+                continue;
+            }
+            let error = mean_error(positions, parameters, candidate);
+            if error < best_error {
+                best_error = error;
+                best_scaling = candidate;
+            }
+        }
+        step /= 2.0;
+    }
+    return best_scaling;
+}
+
+/// the weights as src/eval_parameters.rs
+pub fn rust_source(parameters: &EvalParameters) -> String {
+    let piece_squares: Vec<String> = parameters
+        .piece_squares
+        .iter()
+        .map(|table| format!("        {}", score_array(table, 8)))
+        .collect();
+    let fields = [
+        format!("material: {}", score_array(&parameters.material, 4)),
+        format!("piece_squares: [\n{}\n    ]", piece_squares.join(",\n")),
+        format!("passed_pawns: {}", score_array(&parameters.passed_pawns, 4)),
+        format!("connected_pawns: {}", score_array(&parameters.connected_pawns, 4)),
+        format!("candidate_pawns: {}", score_array(&parameters.candidate_pawns, 4)),
+        format!("isolated_pawn: {}", score_source(parameters.isolated_pawn)),
+        format!("doubled_pawn: {}", score_source(parameters.doubled_pawn)),
+        format!("backward_pawn: {}", score_source(parameters.backward_pawn)),
+        format!("pawn_shield_close: {}", parameters.pawn_shield_close),
+        format!("pawn_shield_far: {}", parameters.pawn_shield_far),
+        format!("pawn_storm: {:?}", parameters.pawn_storm),
+        format!("half_open_file_near_king: {}", parameters.half_open_file_near_king),
+        format!("open_file_near_king: {}", parameters.open_file_near_king),
+        format!("king_attack_weights: {:?}", parameters.king_attack_weights),
+        format!("knight_mobility: {}", score_array(&parameters.knight_mobility, 4)),
+        format!("bishop_mobility: {}", score_array(&parameters.bishop_mobility, 4)),
+        format!("rook_mobility: {}", score_array(&parameters.rook_mobility, 4)),
+        format!("queen_mobility: {}", score_array(&parameters.queen_mobility, 4)),
+    ];
+    let mut source = String::from("// written by `chesstacean tune`, tuning the evaluation replaces this file with the tuned weights\n");
+    source.push_str("use crate::evaluation::{EvalParameters, Score};\n\n");
+    source.push_str("#[rustfmt::skip]\n");
+    source.push_str("pub const DEFAULT_PARAMETERS: EvalParameters = EvalParameters {\n");
+    source.push_str(&fields.map(|field| format!("    {}", field)).join(",\n"));
+    source.push_str("\n};\n");
+    return source;
+}
+
+fn score_source(score: Score) -> String {
+    return format!("Score::new({}, {})", score.middlegame, score.endgame);
+}
+
+/// short arrays on one line, longer ones with eight scores per line
+fn score_array(scores: &[Score], indent: usize) -> String {
+    let items: Vec<String> = scores.iter().map(|score| score_source(*score)).collect();
+    if items.len() <= 8 {
+        return format!("[{}]", items.join(", "));
+    }
+    let lines: Vec<String> = items
+        .chunks(8)
+        .map(|chunk| format!("{}{}", " ".repeat(indent + 4), chunk.join(", ")))
+        .collect();
+    return format!("[\n{}\n{}]", lines.join(",\n"), " ".repeat(indent));
+}
@@ -0,0 +1,176 @@
+use std::io::{self, BufRead};
+use std::time::{Duration, Instant};
+
+use crate::board_state::{BoardState, WHITE};
+use crate::engine::{time_for_move, Engine};
+use crate::search::{SearchLimits, SearchOptions, SearchResult, DEFAULT_HASH_MEGABYTES, MAX_HASH_MEGABYTES, MAX_MULTI_PV, MAX_THREADS, SWITCHES};
+
+/// reads UCI commands from stdin until "quit" or the end of the input
+pub fn run() {
+    let mut engine = Engine::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => print_identification(),
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => engine.new_game(),
+            Some("position") => set_position(&mut engine, &tokens[1..]),
+            Some("go") => go(&mut engine, &tokens[1..]),
+            Some("stop") => engine.stop(),
+            Some("ponderhit") => engine.ponderhit(),
+            Some("setoption") => set_option(&mut engine, &tokens[1..]),
+            Some("quit") => break,
+            // unknown commands are ignored as the protocol asks
+            _ => {}
+        }
+    }
+    engine.stop();
+}
+
+fn print_identification() {
+    println!("id name chesstacean {}", env!("CARGO_PKG_VERSION"));
+    println!("id author the chesstacean authors");
+    println!(
+        "option name Hash type spin default {} min 1 max {}",
+        DEFAULT_HASH_MEGABYTES, MAX_HASH_MEGABYTES
+    );
+    println!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+    println!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV);
+    println!("option name EvalFile type string default <empty>");
+    println!("option name UseNNUE type check default false");
+    println!("option name Ponder type check default false");
+    println!("option name OwnBook type check default false");
+    println!("option name BookFile type string default <empty>");
+    println!("option name BookBestMove type check default false");
+    println!("option name SyzygyPath type string default <empty>");
+    println!("option name TablebasePath type string default <empty>");
+    let mut options = SearchOptions::default();
+    for name in SWITCHES {
+        let default = options.switch_mut(name).expect("every switch has an option");
+        println!("option name {} type check default {}", name, default);
+    }
+    println!("uciok");
+}
+
+/// "startpos" or "fen <fen>", optionally followed by "moves" and the moves played from there
+fn set_position(engine: &mut Engine, tokens: &[&str]) {
+    let moves_index = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    let bs = match tokens.first().copied() {
+        Some("startpos") => BoardState::new(),
+        Some("fen") if moves_index >= 5 => {
+            let mut fields = tokens[1..moves_index].to_vec();
+            // the clocks are optional
+            if fields.len() == 4 {
+                fields.extend(["0", "1"]);
+            }
+            BoardState::from_fen(&fields.join(" "))
+        }
+        _ => {
+            println!("info string invalid position command");
+            return;
+        }
+    };
+    engine.set_position(bs);
+    for move_string in tokens.iter().skip(moves_index + 1) {
+        match engine.position().move_from_string(move_string) {
+            Some(chess_move) => engine.make_move(chess_move),
+            None => {
+                println!("info string illegal move {}", move_string);
+                return;
+            }
+        }
+    }
+}
+
+fn go(engine: &mut Engine, tokens: &[&str]) {
+    let (limits, infinite) = parse_go(tokens, engine.position().color_to_move());
+    let start = Instant::now();
+    let on_iteration = Box::new(move |result: &SearchResult| {
+        let millis = start.elapsed().as_millis() as u64;
+        let nps = result.nodes * 1000 / millis.max(1);
+        for (index, line) in result.lines.iter().enumerate() {
+            println!(
+                "info depth {} multipv {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+                result.depth,
+                index + 1,
+                line.score_string(),
+                result.nodes,
+                nps,
+                result.hashfull,
+                millis,
+                line.pv_string()
+            );
+        }
+    });
+    let on_result = Box::new(|result: SearchResult| {
+        // a null move tells the GUI there is nothing to play
+        let best_move = result.best_move.map(|best_move| best_move.str()).unwrap_or(String::from("0000"));
+        match result.ponder_move() {
+            Some(ponder_move) => println!("bestmove {} ponder {}", best_move, ponder_move.str()),
+            None => println!("bestmove {}", best_move),
+        }
+    });
+    // the protocol doesn't allow a bestmove before stop or ponderhit in these searches
+    engine.go(limits, infinite || limits.ponder, on_iteration, on_result);
+}
+
+/// the limits of a "go" command for the side to move and whether it searches until "stop"
+pub fn parse_go(tokens: &[&str], color_to_move: i32) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::infinite();
+    let mut time_left = [None, None];
+    let mut increment = [Duration::ZERO, Duration::ZERO];
+    let mut moves_to_go = None;
+    let mut move_time = None;
+    let mut infinite = false;
+    let mut tokens = tokens.iter();
+    while let Some(token) = tokens.next() {
+        match *token {
+            "ponder" => limits.ponder = true,
+            "infinite" => infinite = true,
+            name => {
+                // GUIs send negative times once a clock is overstepped, that leaves no time at all
+                let value = tokens.next().and_then(|value| value.parse::<i64>().ok()).map(|value| value.max(0) as u64);
+                let millis = value.map(Duration::from_millis);
+                match name {
+                    "wtime" => time_left[0] = millis,
+                    "btime" => time_left[1] = millis,
+                    "winc" => increment[0] = millis.unwrap_or_default(),
+                    "binc" => increment[1] = millis.unwrap_or_default(),
+                    "movestogo" => moves_to_go = value.map(|moves| moves as u32),
+                    "movetime" => move_time = millis,
+                    "depth" => limits.depth = value.map_or(limits.depth, |depth| (depth as i32).min(limits.depth)),
+                    "nodes" => limits.nodes = value,
+                    _ => {}
+                }
+            }
+        }
+    }
+    let side = if color_to_move == WHITE { 0 } else { 1 };
+    limits.time = match (move_time, time_left[side]) {
+        (Some(move_time), _) => Some(move_time),
+        (None, Some(time_left)) => Some(time_for_move(time_left, increment[side], moves_to_go)),
+        (None, None) => None,
+    };
+    return (limits, infinite);
+}
+
+/// "name <name> value <value>", both can contain spaces
+fn set_option(engine: &mut Engine, tokens: &[&str]) {
+    let value_index = tokens.iter().position(|token| *token == "value").unwrap_or(tokens.len());
+    if tokens.first() != Some(&"name") {
+        println!("info string invalid setoption command");
+        return;
+    }
+    let name = tokens[1..value_index].join(" ");
+    let value = tokens.get(value_index + 1..).map(|value| value.join(" ")).unwrap_or_default();
+    // pondering is up to the GUI, it sends "go ponder" when it wants the engine to ponder
+    if name.to_lowercase() == "ponder" {
+        return;
+    }
+    if let Err(error) = engine.set_option(&name, &value) {
+        println!("info string {}", error);
+    }
+}
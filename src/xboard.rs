@@ -0,0 +1,323 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board_state::{BoardState, BLACK, WHITE};
+use crate::engine::{time_for_move, Engine};
+use crate::search::{SearchLimits, SearchResult, MATE_SCORE, MATE_THRESHOLD};
+use crate::selfplay::GameResult;
+
+// how the protocol writes mate scores, the number of moves to the mate is added to it
+const XBOARD_MATE_SCORE: i32 = 100000;
+
+/// commands and finished searches arrive on the same channel so they are handled one after the other
+pub enum Event {
+    Command(String),
+    // the number of the search it belongs to
+    SearchFinished(u64, SearchResult),
+}
+
+/// the state of the game as the Chess Engine Communication Protocol sees it
+pub struct XBoard {
+    engine: Engine,
+    events: Sender<Event>,
+    // in force mode the engine plays neither side and only checks the moves it's given
+    force: bool,
+    engine_color: i32,
+    post: bool,
+    max_depth: Option<i32>,
+    move_time: Option<Duration>,
+    // 0 when the whole game is one time control
+    moves_per_session: u32,
+    increment: Duration,
+    time_left: Option<Duration>,
+    opponent_time_left: Option<Duration>,
+    search_count: u64,
+    // the search whose move is played when it finishes, a search that was cancelled doesn't play its move
+    current_search: Option<u64>,
+}
+
+/// reads CECP commands from stdin until "quit" or the end of the input
+pub fn run() {
+    let (events, receiver) = mpsc::channel();
+    let input = events.clone();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if input.send(Event::Command(line)).is_err() {
+                return;
+            }
+        }
+        let _ = input.send(Event::Command(String::from("quit")));
+    });
+    let mut xboard = XBoard::new(events);
+    for event in receiver {
+        match event {
+            Event::Command(line) => {
+                if !xboard.handle_command(&line) {
+                    break;
+                }
+            }
+            Event::SearchFinished(id, result) => xboard.search_finished(id, result),
+        }
+    }
+    xboard.cancel_search();
+}
+
+impl XBoard {
+    /// finished searches are sent to the channel and have to be passed back to search_finished
+    pub fn new(events: Sender<Event>) -> XBoard {
+        return XBoard {
+            engine: Engine::new(),
+            events,
+            force: false,
+            engine_color: BLACK,
+            post: false,
+            max_depth: None,
+            move_time: None,
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            time_left: None,
+            opponent_time_left: None,
+            search_count: 0,
+            current_search: None,
+        };
+    }
+
+    pub fn position(&self) -> &BoardState {
+        return self.engine.position();
+    }
+
+    /// false once the engine should quit
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(command) = tokens.first().copied() else {
+            return true;
+        };
+        let argument = tokens.get(1).copied().unwrap_or("");
+        match command {
+            "protover" => {
+                println!(
+                    "feature myname=\"chesstacean {}\" setboard=1 usermove=1 ping=1 playother=1 memory=1 smp=1 sigint=0 sigterm=0 analyze=0 colors=0 reuse=1 egt=\"syzygy,chesstacean\"",
+                    env!("CARGO_PKG_VERSION")
+                );
+                println!("feature done=1");
+            }
+            "new" => {
+                self.cancel_search();
+                self.engine.set_position(BoardState::new());
+                self.engine.new_game();
+                self.force = false;
+                self.engine_color = BLACK;
+                self.max_depth = None;
+                self.move_time = None;
+            }
+            "setboard" => {
+                self.cancel_search();
+                let mut fields = tokens[1..].to_vec();
+                // the clocks are optional
+                if fields.len() == 4 {
+                    fields.extend(["0", "1"]);
+                }
+                let Ok(bs) = BoardState::try_from_fen(&fields.join(" ")) else {
+                    println!("tellusererror Illegal position");
+                    return true;
+                };
+                self.engine.set_position(bs);
+            }
+            "usermove" => self.user_move(argument),
+            "go" => {
+                self.force = false;
+                self.engine_color = self.engine.position().color_to_move();
+                self.start_search();
+            }
+            "playother" => {
+                self.force = false;
+                self.engine_color = if self.engine.position().color_to_move() == WHITE { BLACK } else { WHITE };
+            }
+            "force" => {
+                self.cancel_search();
+                self.force = true;
+            }
+            "?" => self.engine.stop(),
+            "level" => self.set_level(&tokens[1..]),
+            "st" => self.move_time = argument.parse().ok().map(Duration::from_secs_f64),
+            "sd" => self.max_depth = argument.parse().ok(),
+            // the clocks are given in centiseconds
+            "time" => self.time_left = parse_centiseconds(argument),
+            "otim" => self.opponent_time_left = parse_centiseconds(argument),
+            "undo" => {
+                self.cancel_search();
+                self.engine.undo();
+            }
+            "remove" => {
+                self.cancel_search();
+                self.engine.undo();
+                self.engine.undo();
+            }
+            "result" => {
+                self.cancel_search();
+                self.force = true;
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "ping" => println!("pong {}", argument),
+            "memory" => self.set_option("Hash", argument),
+            "cores" => self.set_option("Threads", argument),
+            "egtpath" if argument == "syzygy" => self.set_option("SyzygyPath", &tokens[2..].join(" ")),
+            "egtpath" if argument == "chesstacean" => self.set_option("TablebasePath", &tokens[2..].join(" ")),
+            "quit" => return false,
+            "xboard" | "accepted" | "rejected" | "hard" | "easy" | "random" | "computer" | "name" | "rating" => {}
+            // without the usermove feature moves come on their own
+            _ if self.engine.position().move_from_string(command).is_some() => self.user_move(command),
+            _ => println!("Error (unknown command): {}", command),
+        }
+        return true;
+    }
+
+    fn user_move(&mut self, move_string: &str) {
+        let Some(chess_move) = self.engine.position().move_from_string(move_string) else {
+            println!("Illegal move: {}", move_string);
+            return;
+        };
+        self.cancel_search();
+        self.engine.make_move(chess_move);
+        if !self.force && self.engine.position().color_to_move() == self.engine_color {
+            self.start_search();
+        }
+    }
+
+    /// "level <moves per session> <minutes or minutes:seconds> <increment in seconds>"
+    fn set_level(&mut self, arguments: &[&str]) {
+        let [moves, base, increment] = arguments else {
+            println!("Error (wrong number of arguments): level");
+            return;
+        };
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        let base_seconds = minutes.parse::<u64>().unwrap_or(0) * 60 + seconds.parse::<u64>().unwrap_or(0);
+        // until the first "time" command the whole base time is left
+        self.time_left = Some(Duration::from_secs(base_seconds));
+        self.opponent_time_left = self.time_left;
+        self.moves_per_session = moves.parse().unwrap_or(0);
+        self.increment = increment.parse().ok().map(Duration::from_secs_f64).unwrap_or_default();
+        self.move_time = None;
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        self.cancel_search();
+        if let Err(error) = self.engine.set_option(name, value) {
+            println!("tellusererror {}", error);
+        }
+    }
+
+    /// the limits the next search of the engine gets from the time control
+    pub fn search_limits(&self) -> SearchLimits {
+        let mut limits = SearchLimits::infinite();
+        if let Some(depth) = self.max_depth {
+            limits.depth = depth.clamp(1, limits.depth);
+        }
+        limits.time = match (self.move_time, self.time_left) {
+            (Some(move_time), _) => Some(move_time),
+            (None, Some(time_left)) => {
+                // the moves of the engine so far, the own moves are every second one
+                let moves_to_go = if self.moves_per_session > 0 {
+                    Some(self.moves_per_session - (self.engine.moves_played() as u32 / 2) % self.moves_per_session)
+                } else {
+                    None
+                };
+                Some(self.catch_up(time_for_move(time_left, self.increment, moves_to_go), time_left))
+            }
+            (None, None) => None,
+        };
+        return limits;
+    }
+
+    /// behind on the clock the engine plays faster to catch up with the opponent, but never below half its time
+    fn catch_up(&self, time: Duration, time_left: Duration) -> Duration {
+        let Some(opponent_time_left) = self.opponent_time_left else {
+            return time;
+        };
+        if time_left >= opponent_time_left || opponent_time_left.is_zero() {
+            return time;
+        }
+        return time.mul_f64(time_left.as_secs_f64() / opponent_time_left.as_secs_f64()).max(time / 2);
+    }
+
+    fn start_search(&mut self) {
+        if self.engine.game_result().is_some() {
+            return;
+        }
+        let limits = self.search_limits();
+        self.search_count += 1;
+        self.current_search = Some(self.search_count);
+        let id = self.search_count;
+        let post = self.post;
+        let start = Instant::now();
+        let on_iteration = Box::new(move |result: &SearchResult| {
+            if post {
+                let centiseconds = start.elapsed().as_millis() / 10;
+                println!(
+                    "{} {} {} {} {}",
+                    result.depth,
+                    xboard_score(result.score),
+                    centiseconds,
+                    result.nodes,
+                    result.pv_string()
+                );
+            }
+        });
+        let events = self.events.clone();
+        let on_result = Box::new(move |result: SearchResult| {
+            let _ = events.send(Event::SearchFinished(id, result));
+        });
+        self.engine.go(limits, false, on_iteration, on_result);
+    }
+
+    /// stops the search without playing its move
+    fn cancel_search(&mut self) {
+        self.current_search = None;
+        self.engine.stop();
+    }
+
+    pub fn search_finished(&mut self, id: u64, result: SearchResult) {
+        if self.current_search != Some(id) {
+            return;
+        }
+        self.current_search = None;
+        self.engine.wait();
+        let Some(best_move) = result.best_move else {
+            return;
+        };
+        self.engine.make_move(best_move);
+        println!("move {}", best_move.str());
+        match self.engine.game_result() {
+            Some(GameResult::WhiteWins) => println!("1-0 {{White mates}}"),
+            Some(GameResult::BlackWins) => println!("0-1 {{Black mates}}"),
+            Some(GameResult::Draw) => println!("1/2-1/2 {{Draw}}"),
+            None => {}
+        }
+    }
+}
+
+/// the clocks of time and otim are sent in centiseconds
+fn parse_centiseconds(argument: &str) -> Option<Duration> {
+    // the clocks can run below zero when a side oversteps the time
+    return argument
+        .parse::<i64>()
+        .ok()
+        .map(|centiseconds| Duration::from_millis(centiseconds.max(0) as u64 * 10));
+}
+
+/// centipawns, mates are written as 100000 plus the moves to the mate
+fn xboard_score(score: i32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        return XBOARD_MATE_SCORE + (MATE_SCORE - score + 1) / 2;
+    }
+    if score <= -MATE_THRESHOLD {
+        return -XBOARD_MATE_SCORE - (MATE_SCORE + score) / 2;
+    }
+    return score;
+}
@@ -0,0 +1,34 @@
+use crate::board_state::{BLACK, PIECE_MASK};
+
+// 12 pieces on 64 squares, 4 castling rights, 8 en-passant files and the side to move
+const KEY_COUNT: usize = 12 * 64 + 4 + 8 + 1;
+
+const KEYS: [u64; KEY_COUNT] = generate_keys(0x2545_F491_4F6C_DD1D);
+
+pub const CASTLING_KEYS: [u64; 4] = [KEYS[768], KEYS[769], KEYS[770], KEYS[771]];
+pub const EN_PASSANT_KEYS: [u64; 8] = [KEYS[772], KEYS[773], KEYS[774], KEYS[775], KEYS[776], KEYS[777], KEYS[778], KEYS[779]];
+pub const BLACK_TO_MOVE_KEY: u64 = KEYS[780];
+
+/// key for a colored piece standing on the square with the given index
+pub fn piece_key(piece: i32, square: usize) -> u64 {
+    let mut piece_index = ((piece & PIECE_MASK) - 1) as usize;
+    if piece & BLACK != 0 {
+        piece_index += 6;
+    }
+    return KEYS[piece_index * 64 + square];
+}
+
+/// xorshift64* so the keys are the same on every run without needing a random number crate
+const fn generate_keys(seed: u64) -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state = seed;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        keys[i] = state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        i += 1;
+    }
+    return keys;
+}
@@ -0,0 +1,91 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::fs;
+
+use chesstacean::board_state::BoardState;
+use chesstacean::book_builder::{self, BookOptions};
+use chesstacean::polyglot::{decode_move, encode_move, Book};
+use chesstacean::selfplay::GameResult;
+
+const GAMES: &str = "1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 0-1\n\n1. d4 d5 1/2-1/2\n\n1. c4 *\n";
+
+/// the moves of the book built from GAMES with the weights, the highest weight first
+fn book_moves(name: &str, options: &BookOptions, bs: &BoardState) -> Vec<(String, u16)> {
+    let directory = env::temp_dir().join(format!("chesstacean_book_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&directory).expect("temporary directory");
+    let pgn_path = directory.join("games.pgn").to_str().expect("valid path").to_string();
+    let book_path = directory.join("book.bin").to_str().expect("valid path").to_string();
+    fs::write(&pgn_path, GAMES).expect("games written");
+    book_builder::build(&[pgn_path], &book_path, options).expect("book written");
+    let book = Book::open(&book_path).expect("a valid book");
+    fs::remove_dir_all(&directory).expect("temporary directory removed");
+    return book
+        .moves(bs)
+        .into_iter()
+        .map(|(chess_move, weight)| (chess_move.str(), weight))
+        .collect();
+}
+
+fn after(moves: &[&str]) -> BoardState {
+    let mut bs = BoardState::new();
+    for uci in moves {
+        bs = bs.make_move(bs.move_from_string(uci).unwrap());
+    }
+    return bs;
+}
+
+#[test]
+fn moves_are_weighted_by_their_results() {
+    let options = BookOptions::default();
+    // e4 won once and lost once, d4 was drawn, the unfinished game isn't used
+    let moves = book_moves("results", &options, &BoardState::new());
+    assert_eq!(moves, vec![(String::from("e2e4"), 2), (String::from("d2d4"), 1)]);
+    // black lost with e5, the move stays in the book with weight 0 so it's never played
+    let moves = book_moves("losses", &options, &after(&["e2e4"]));
+    assert_eq!(moves, vec![(String::from("c7c5"), 2), (String::from("e7e5"), 0)]);
+}
+
+#[test]
+fn options_filter_the_moves() {
+    let first_move = BookOptions {
+        max_plies: 1,
+        ..BookOptions::default()
+    };
+    assert_eq!(book_moves("plies", &first_move, &BoardState::new()).len(), 2);
+    assert!(book_moves("plies", &first_move, &after(&["e2e4"])).is_empty());
+    let two_games = BookOptions {
+        min_games: 2,
+        ..BookOptions::default()
+    };
+    assert_eq!(book_moves("games", &two_games, &BoardState::new()), vec![(String::from("e2e4"), 2)]);
+    let white_wins = BookOptions {
+        results: vec![GameResult::WhiteWins],
+        ..BookOptions::default()
+    };
+    assert_eq!(book_moves("wins", &white_wins, &BoardState::new()), vec![(String::from("e2e4"), 2)]);
+    assert_eq!(book_moves("wins", &white_wins, &after(&["e2e4"])), vec![(String::from("e7e5"), 0)]);
+}
+
+#[test]
+fn encoded_moves_decode_to_the_same_move() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        "8/2p5/3p4/KP5r/1R3pPk/8/4P3/8 b - g3 0 1",
+    ];
+    for fen in fens {
+        let bs = BoardState::from_fen(fen);
+        for chess_move in bs.get_legal_moves() {
+            assert_eq!(
+                decode_move(&bs, encode_move(&bs, chess_move)),
+                Some(chess_move),
+                "{} {}",
+                fen,
+                chess_move.str()
+            );
+        }
+    }
+}
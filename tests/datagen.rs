@@ -0,0 +1,30 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::fs;
+
+use chesstacean::datagen;
+use chesstacean::tuner;
+
+#[test]
+fn generated_positions_can_be_tuned_on() {
+    let directory = env::temp_dir().join(format!("chesstacean_datagen_{}", std::process::id()));
+    fs::create_dir_all(&directory).expect("temporary directory");
+    let path = directory.join("positions.txt");
+    let path = path.to_str().expect("valid path");
+    datagen::generate(path, 2, 100, 5).expect("games played");
+    let lines: Vec<String> = fs::read_to_string(path).expect("file written").lines().map(String::from).collect();
+    assert!(!lines.is_empty());
+    let positions = tuner::load_positions(path).expect("file readable");
+    assert_eq!(positions.len(), lines.len());
+    for (line, position) in lines.iter().zip(&positions) {
+        let fields: Vec<&str> = line.split(" | ").collect();
+        assert_eq!(fields.len(), 3, "{}", line);
+        // the tuner leaves out the clocks
+        let without_clocks = |fen: &str| fen.split(' ').take(4).collect::<Vec<&str>>().join(" ");
+        assert_eq!(without_clocks(&position.bs.to_fen()), without_clocks(fields[0]));
+        assert_eq!(position.result, fields[2].parse::<f64>().expect("a result"));
+    }
+    fs::remove_dir_all(&directory).expect("temporary directory removed");
+}
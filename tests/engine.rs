@@ -0,0 +1,105 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use chesstacean::board_state::BoardState;
+use chesstacean::engine::Engine;
+use chesstacean::search::{SearchLimits, SearchResult};
+
+const MIDDLEGAME: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+
+// long enough for a search that is held back to have finished on its own
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// starts a search on the engine, the result arrives on the receiver
+fn go(engine: &mut Engine, limits: SearchLimits, hold_result: bool) -> Receiver<SearchResult> {
+    let (results, received) = mpsc::channel();
+    let on_result = Box::new(move |result: SearchResult| {
+        results.send(result).expect("the test waits for the result");
+    });
+    engine.go(limits, hold_result, Box::new(|_: &SearchResult| {}), on_result);
+    return received;
+}
+
+fn ponder(depth: i32) -> SearchLimits {
+    return SearchLimits {
+        ponder: true,
+        ..SearchLimits::depth(depth)
+    };
+}
+
+/// the best move and the expected answer are legal one after the other
+fn assert_legal_line(bs: &BoardState, result: &SearchResult) {
+    let best_move = result.best_move.expect("a best move");
+    assert!(bs.get_legal_moves().contains(&best_move));
+    let ponder_move = result.ponder_move().expect("an expected answer");
+    assert_eq!(Some(ponder_move), result.pv.get(1).copied());
+    assert!(bs.make_move(best_move).get_legal_moves().contains(&ponder_move));
+}
+
+#[test]
+fn ponderhit_releases_the_held_result() {
+    let mut engine = Engine::new();
+    engine.set_option("Threads", "1").expect("valid option");
+    engine.set_position(BoardState::from_fen(MIDDLEGAME));
+    let received = go(&mut engine, ponder(4), true);
+    // the search is done long before, but the result waits for the opponent's move
+    assert!(received.recv_timeout(SETTLE_TIME).is_err());
+    engine.ponderhit();
+    let result = received.recv_timeout(Duration::from_secs(10)).expect("the result after ponderhit");
+    assert_eq!(result.depth, 4);
+    assert_legal_line(engine.position(), &result);
+    engine.wait();
+}
+
+#[test]
+fn ponderhit_starts_the_clock() {
+    let mut engine = Engine::new();
+    engine.set_option("Threads", "1").expect("valid option");
+    engine.set_position(BoardState::from_fen(MIDDLEGAME));
+    let limits = SearchLimits {
+        ponder: true,
+        ..SearchLimits::time(Duration::from_millis(100))
+    };
+    let received = go(&mut engine, limits, true);
+    // with the clock paused the time limit doesn't run out
+    assert!(received.recv_timeout(SETTLE_TIME).is_err());
+    engine.ponderhit();
+    let result = received.recv_timeout(Duration::from_secs(10)).expect("the result once the time is up");
+    assert_legal_line(engine.position(), &result);
+    engine.wait();
+}
+
+#[test]
+fn ponder_miss_stops_and_searches_the_played_move() {
+    let mut engine = Engine::new();
+    engine.set_option("Threads", "1").expect("valid option");
+    let bs = BoardState::from_fen(MIDDLEGAME);
+    engine.set_position(bs.clone());
+    let result = go(&mut engine, SearchLimits::depth(4), false).recv().expect("a result");
+    assert_legal_line(&bs, &result);
+    let best_move = result.best_move.unwrap();
+    let expected = result.ponder_move().unwrap();
+    // ponder on the expected answer, the opponent plays something else
+    engine.make_move(best_move);
+    engine.make_move(expected);
+    let pondering = go(&mut engine, ponder(SearchLimits::infinite().depth), true);
+    assert!(pondering.recv_timeout(SETTLE_TIME).is_err());
+    engine.stop();
+    // the pondering search hands over what it has when it is stopped
+    assert!(pondering.recv_timeout(Duration::from_secs(10)).is_ok());
+    engine.undo();
+    let after_best_move = engine.position().clone();
+    let played = after_best_move
+        .get_legal_moves()
+        .into_iter()
+        .find(|&chess_move| chess_move != expected)
+        .unwrap();
+    engine.make_move(played);
+    let received = go(&mut engine, SearchLimits::depth(3), false);
+    let result = received.recv_timeout(Duration::from_secs(10)).expect("a result for the new position");
+    assert_legal_line(&after_best_move.make_move(played), &result);
+    engine.wait();
+}
@@ -0,0 +1,43 @@
+use chesstacean::board_state::{BoardState, CASTLING_TEST, CHECKMATE_TEST, E4_FEN, KING, PAWN, STARTING_FEN};
+use chesstacean::evaluation::{evaluate, EvalParameters};
+
+const POSITIONS: [&str; 7] = [
+    STARTING_FEN,
+    E4_FEN,
+    CASTLING_TEST,
+    CHECKMATE_TEST,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+];
+
+#[test]
+fn evaluation_is_symmetric_under_color_flip() {
+    for fen in POSITIONS {
+        let bs = BoardState::from_fen(fen);
+        assert_eq!(evaluate(&bs), evaluate(&bs.mirrored()), "{}", fen);
+    }
+}
+
+#[test]
+fn starting_position_is_balanced() {
+    assert_eq!(evaluate(&BoardState::from_fen(STARTING_FEN)), 0);
+}
+
+#[test]
+fn only_weights_the_evaluation_reads_are_tuned() {
+    let mut parameters = EvalParameters::default();
+    for value in parameters.values_mut() {
+        *value += 1000;
+    }
+    let defaults = EvalParameters::default();
+    let (king, pawn) = (KING as usize, PAWN as usize);
+    assert_eq!(parameters.material[0], defaults.material[0]);
+    assert_eq!(parameters.material[king], defaults.material[king]);
+    assert_ne!(parameters.material[pawn], defaults.material[pawn]);
+    assert_eq!(parameters.piece_squares[0], defaults.piece_squares[0]);
+    assert_ne!(parameters.piece_squares[king], defaults.piece_squares[king]);
+    for piece in [0, pawn, king] {
+        assert_eq!(parameters.king_attack_weights[piece], defaults.king_attack_weights[piece]);
+    }
+}
@@ -0,0 +1,81 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::{self, BoardState};
+use chesstacean::nnue::Network;
+
+const ACCUMULATOR_SIZE: usize = 8;
+const HIDDEN_SIZE: usize = 4;
+
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        return self.state >> 16;
+    }
+}
+
+/// a HalfKA network with random weights over the whole i16 range, so the accumulators overflow all the time
+fn random_network(random: &mut Random) -> Network {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"CTNN");
+    for value in [1u32, 1, ACCUMULATOR_SIZE as u32, HIDDEN_SIZE as u32] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for _ in 0..(64 * 12 * 64 + 1) * ACCUMULATOR_SIZE {
+        bytes.extend_from_slice(&(random.next() as i16).to_le_bytes());
+    }
+    for _ in 0..2 * ACCUMULATOR_SIZE * HIDDEN_SIZE {
+        bytes.push(random.next() as u8);
+    }
+    for _ in 0..HIDDEN_SIZE {
+        bytes.extend_from_slice(&(random.next() as i32).to_le_bytes());
+    }
+    for _ in 0..HIDDEN_SIZE {
+        bytes.push(random.next() as u8);
+    }
+    bytes.extend_from_slice(&(random.next() as i32).to_le_bytes());
+    return Network::from_bytes(&bytes).expect("valid network");
+}
+
+#[test]
+fn updated_accumulators_match_refreshed_ones() {
+    let mut random = Random { state: 3 };
+    let network = random_network(&mut random);
+    let fens = [
+        board_state::STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3pPk/8/4P3/8 b - g3 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    ];
+    for fen in fens {
+        for _ in 0..10 {
+            let mut bs = BoardState::from_fen(fen);
+            let mut accumulator = network.new_accumulator();
+            network.refresh(&bs, &mut accumulator);
+            for _ in 0..40 {
+                let moves = bs.get_legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                let chess_move = moves[random.next() as usize % moves.len()];
+                let new_state = bs.make_move(chess_move);
+                let mut updated = network.new_accumulator();
+                network.update(&bs, Some(chess_move), &new_state, &accumulator, &mut updated);
+                let mut refreshed = network.new_accumulator();
+                network.refresh(&new_state, &mut refreshed);
+                assert_eq!(updated, refreshed, "{} {}", bs.to_fen(), chess_move.str());
+                network.evaluate(&updated, new_state.color_to_move());
+                bs = new_state;
+                accumulator = updated;
+            }
+            let passed = bs.make_null_move();
+            let mut updated = network.new_accumulator();
+            network.update(&bs, None, &passed, &accumulator, &mut updated);
+            assert_eq!(updated, accumulator);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::BoardState;
+use chesstacean::evaluation::{evaluate, EvalParameters};
+use chesstacean::pawn_structure::{evaluate_pawns, PawnEntry, PawnTable};
+
+fn white_pawns(fen: &str) -> PawnEntry {
+    return evaluate_pawns(&BoardState::from_fen(fen), &EvalParameters::default());
+}
+
+#[test]
+fn passed_and_isolated_pawns() {
+    let parameters = EvalParameters::default();
+    let entry = white_pawns("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1");
+    assert_eq!(entry.passed_pawns[0], 1 << 35);
+    assert_eq!(entry.passed[0], parameters.passed_pawns[4]);
+    assert_eq!(entry.structure[0], parameters.isolated_pawn);
+    // a pawn on the next file can still stop it
+    let entry = white_pawns("4k3/4p3/8/3P4/8/8/8/4K3 w - - 0 1");
+    assert_eq!(entry.passed_pawns[0], 0);
+}
+
+#[test]
+fn doubled_pawns() {
+    let parameters = EvalParameters::default();
+    let entry = white_pawns("4k3/8/8/8/3P4/8/3P4/4K3 w - - 0 1");
+    // only the front pawn can be passed
+    assert_eq!(entry.passed_pawns[0], 1 << 27);
+    assert_eq!(
+        entry.structure[0],
+        parameters.isolated_pawn + parameters.isolated_pawn + parameters.doubled_pawn
+    );
+}
+
+#[test]
+fn supported_and_backward_pawns() {
+    let parameters = EvalParameters::default();
+    let entry = white_pawns("4k3/8/8/8/3P4/2P5/8/4K3 w - - 0 1");
+    assert_eq!(entry.structure[0], parameters.connected_pawns[3]);
+    // d3 can't be protected by the pawn on c4 anymore and e5 controls d4
+    let entry = white_pawns("4k3/8/8/4p3/2P5/3P4/8/4K3 w - - 0 1");
+    assert_eq!(entry.structure[0], parameters.backward_pawn + parameters.connected_pawns[3]);
+}
+
+#[test]
+fn pawns_on_the_back_rows_are_evaluated() {
+    let bs = BoardState::from_fen("P3k2p/8/8/8/8/8/8/p3K2P w - - 0 1");
+    let entry = evaluate_pawns(&bs, &EvalParameters::default());
+    assert_eq!(entry.structure[0], entry.structure[1]);
+    assert_eq!(evaluate(&bs), evaluate(&bs.mirrored()));
+}
+
+#[test]
+fn pawn_structures_are_found_in_the_table() {
+    let mut table = PawnTable::new();
+    let bs = BoardState::from_fen("4k3/8/8/4p3/2P5/3P4/8/4K3 w - - 0 1");
+    let first = table.probe(&bs);
+    assert_eq!(table.hits(), 0);
+    // the kings don't change the pawn key
+    let moved_kings = BoardState::from_fen("3k4/8/8/4p3/2P5/3P4/8/3K4 w - - 0 1");
+    let second = table.probe(&moved_kings);
+    assert_eq!(table.hits(), 1);
+    assert_eq!(first.structure, second.structure);
+    table.probe(&BoardState::from_fen("4k3/8/8/4p3/2P5/8/3P4/4K3 w - - 0 1"));
+    assert_eq!(table.hits(), 1);
+}
@@ -0,0 +1,112 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::{BoardState, Move};
+use chesstacean::pgn::parse_games;
+use chesstacean::selfplay::GameResult;
+
+fn uci_moves(moves: &[Move]) -> Vec<String> {
+    return moves.iter().map(|chess_move| chess_move.str()).collect();
+}
+
+#[test]
+fn comments_variations_and_annotations_are_skipped() {
+    let text = r#"[Event "Test"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 {the king's pawn} e5 (1... c5 2. Nf3 (2. c3 {Alapin}) d6) 2. Nf3 $1 ; a comment to the end of the line 3. d4
+Nc6!? 3. Bb5 a6 1-0
+"#;
+    let games = parse_games(text);
+    assert_eq!(games.len(), 1);
+    let game = &games[0];
+    assert_eq!(game.error, None);
+    assert_eq!(game.result, Some(GameResult::WhiteWins));
+    assert_eq!(uci_moves(&game.moves), ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"]);
+}
+
+#[test]
+fn games_are_split_at_results_and_tags() {
+    let text = r#"[Result "0-1"]
+1. f3 e5 2. g4 Qh4# 0-1
+
+[Result "1/2-1/2"]
+1. d4 d5 1/2-1/2
+
+[Result "*"]
+1. c4
+
+[Event "no result at the end"]
+1. Nf3 Nf6
+"#;
+    let games = parse_games(text);
+    let results: Vec<Option<GameResult>> = games.iter().map(|game| game.result).collect();
+    assert_eq!(results, [Some(GameResult::BlackWins), Some(GameResult::Draw), None, None]);
+    assert_eq!(uci_moves(&games[0].moves), ["f2f3", "e7e5", "g2g4", "d8h4"]);
+    assert_eq!(uci_moves(&games[2].moves), ["c2c4"]);
+    assert_eq!(uci_moves(&games[3].moves), ["g1f3", "g8f6"]);
+}
+
+#[test]
+fn games_can_start_from_a_position() {
+    let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+    let text = format!("[FEN \"{}\"]\n[Result \"1-0\"]\n\n1. a8=Q+ Kd7 2. Qb7+ 1-0\n", fen);
+    let games = parse_games(&text);
+    assert_eq!(games[0].start.to_fen(), fen);
+    assert_eq!(uci_moves(&games[0].moves), ["a7a8q", "e8d7", "a8b7"]);
+    // a black move number continues from the position
+    let fen = "4k3/8/8/8/8/8/p7/4K3 b - - 0 1";
+    let text = format!("[FEN \"{}\"]\n\n1... a1=N 2. Kd2 *\n", fen);
+    let games = parse_games(&text);
+    assert_eq!(uci_moves(&games[0].moves), ["a2a1n", "e1d2"]);
+}
+
+#[test]
+fn illegal_moves_end_the_game_with_an_error() {
+    let games = parse_games("1. e4 e5 2. Ke3 Nc6 1-0\n");
+    assert_eq!(uci_moves(&games[0].moves), ["e2e4", "e7e5"]);
+    assert!(games[0].error.as_deref().is_some_and(|error| error.contains("Ke3")));
+    assert_eq!(games[0].result, Some(GameResult::WhiteWins));
+}
+
+#[test]
+fn san_moves() {
+    // both knights can go to d2, both rooks to e3 and the pawn on c7 can take or promote
+    let bs = BoardState::from_fen("1n2k3/2P5/8/8/8/2R3R1/4K3/1N3N2 w - - 0 1");
+    let san = |san: &str| bs.move_from_san(san).map(|chess_move| chess_move.str());
+    assert_eq!(san("Nd2"), None);
+    assert_eq!(san("Nbd2"), Some(String::from("b1d2")));
+    assert_eq!(san("Nfd2"), Some(String::from("f1d2")));
+    assert_eq!(san("N1d2"), None);
+    assert_eq!(san("Re3"), None);
+    assert_eq!(san("Rce3"), Some(String::from("c3e3")));
+    assert_eq!(san("Rg3e3"), Some(String::from("g3e3")));
+    assert_eq!(san("Rc4"), Some(String::from("c3c4")));
+    assert_eq!(san("c8=Q+"), Some(String::from("c7c8q")));
+    assert_eq!(san("c8N"), Some(String::from("c7c8n")));
+    assert_eq!(san("cxb8=R"), Some(String::from("c7b8r")));
+    assert_eq!(san("c8"), None);
+    assert_eq!(san("Kd1!"), Some(String::from("e2d1")));
+    assert_eq!(san("Ke4"), None);
+    assert_eq!(san("O-O"), None);
+    // rows tell the rooks on one file apart
+    let bs = BoardState::from_fen("4k3/8/R7/8/8/8/8/R3K3 w - - 0 1");
+    assert_eq!(bs.move_from_san("Ra3"), None);
+    assert_eq!(bs.move_from_san("R6a3").map(|chess_move| chess_move.str()), Some(String::from("a6a3")));
+    assert_eq!(bs.move_from_san("R1xa3").map(|chess_move| chess_move.str()), Some(String::from("a1a3")));
+}
+
+#[test]
+fn san_castling() {
+    let bs = BoardState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+    assert_eq!(bs.move_from_san("O-O").map(|chess_move| chess_move.str()), Some(String::from("e8g8")));
+    assert_eq!(bs.move_from_san("0-0-0").map(|chess_move| chess_move.str()), Some(String::from("e8c8")));
+    // without the right or through an attacked square it isn't legal
+    let bs = BoardState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b Qk - 0 1");
+    assert!(bs.move_from_san("O-O").is_some());
+    assert_eq!(bs.move_from_san("O-O-O"), None);
+    let bs = BoardState::from_fen("r3k2r/8/8/8/8/8/8/R3KR2 b KQkq - 0 1");
+    assert_eq!(bs.move_from_san("O-O"), None);
+}
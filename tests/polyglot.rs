@@ -0,0 +1,90 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::{BoardState, Position};
+use chesstacean::polyglot::{decode_move, encode_move, Book, BookSelection};
+
+/// the published keys of the Polyglot book format
+#[test]
+fn published_keys() {
+    let keys = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+        ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+        ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+        ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+        // white can take en passant, the file counts
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+        ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+        ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+        ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+    ];
+    for (fen, key) in keys {
+        assert_eq!(BoardState::from_fen(fen).polyglot_key(), key, "{}", fen);
+    }
+    // the keys don't depend on how the position was reached
+    let bs = BoardState::new().make_move(BoardState::new().move_from_string("e2e4").unwrap());
+    assert_eq!(bs.polyglot_key(), 0x823c9b50fd114196);
+}
+
+fn raw_move(uci: &str) -> u16 {
+    let from = Position::index_from_position(Position::position_from_string(&uci[0..2])) as u16;
+    let to = Position::index_from_position(Position::position_from_string(&uci[2..4])) as u16;
+    return from << 6 | to;
+}
+
+#[test]
+fn castling_is_the_king_taking_its_rook() {
+    let bs = BoardState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let short = bs.move_from_string("e1g1").unwrap();
+    assert_eq!(decode_move(&bs, raw_move("e1h1")), Some(short));
+    assert_eq!(encode_move(&bs, short), raw_move("e1h1"));
+    let long = bs.move_from_string("e1c1").unwrap();
+    assert_eq!(decode_move(&bs, raw_move("e1a1")), Some(long));
+    assert_eq!(encode_move(&bs, long), raw_move("e1a1"));
+    // a king next to its rook just moves there
+    let bs = BoardState::from_fen("4k3/8/8/8/8/8/8/R3K2R w - - 0 1");
+    assert_eq!(decode_move(&bs, raw_move("e1f1")), bs.move_from_string("e1f1"));
+    assert_eq!(decode_move(&bs, raw_move("e1h1")), None);
+    // a promotion to a knight
+    let bs = BoardState::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+    let promotion = bs.move_from_string("a7a8n").unwrap();
+    assert_eq!(decode_move(&bs, 1 << 12 | raw_move("a7a8")), Some(promotion));
+    assert_eq!(encode_move(&bs, promotion), 1 << 12 | raw_move("a7a8"));
+}
+
+/// a book with the moves and weights for the position
+fn book(bs: &BoardState, moves: &[(&str, u16)]) -> Book {
+    let mut bytes = Vec::new();
+    for (uci, weight) in moves {
+        bytes.extend(bs.polyglot_key().to_be_bytes());
+        bytes.extend(raw_move(uci).to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+    }
+    return Book::from_bytes(&bytes).expect("a valid book");
+}
+
+#[test]
+fn moves_are_picked_by_weight() {
+    let bs = BoardState::new();
+    let book = book(&bs, &[("d2d4", 1), ("e2e4", 3), ("g1f3", 0)]);
+    let e4 = bs.move_from_string("e2e4").unwrap();
+    let d4 = bs.move_from_string("d2d4").unwrap();
+    assert_eq!(book.moves(&bs).first(), Some(&(e4, 3)));
+    assert_eq!(book.choose_move(&bs, BookSelection::Best), Some(e4));
+    let picks = 4000;
+    let mut e4_picks = 0;
+    for _ in 0..picks {
+        let chess_move = book.choose_move(&bs, BookSelection::WeightedRandom);
+        // a move with weight 0 is never played
+        assert!(chess_move == Some(e4) || chess_move == Some(d4));
+        if chess_move == Some(e4) {
+            e4_picks += 1;
+        }
+    }
+    let share = e4_picks as f64 / picks as f64;
+    assert!((0.7..0.8).contains(&share), "{}", share);
+    // other positions aren't in the book
+    assert_eq!(book.choose_move(&bs.make_move(e4), BookSelection::Best), None);
+}
@@ -0,0 +1,130 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::{self, BoardState};
+use chesstacean::problem::{SolutionNode, Solver, Stipulation};
+
+/// every defense is answered and every line ends in mate within the moves
+fn assert_forces_mate(bs: &BoardState, node: &SolutionNode, moves: u32) {
+    let new_state = bs.make_move(node.chess_move);
+    if new_state.is_checkmate() {
+        return;
+    }
+    assert!(moves > 1, "{} doesn't mate in {}", node.chess_move.str(), bs.to_fen());
+    assert_eq!(node.replies.len(), new_state.get_legal_moves().len());
+    for defense in &node.replies {
+        let defended = new_state.make_move(defense.chess_move);
+        assert!(
+            !defense.replies.is_empty(),
+            "no answer to {} in {}",
+            defense.chess_move.str(),
+            new_state.to_fen()
+        );
+        for continuation in &defense.replies {
+            assert_forces_mate(&defended, continuation, moves - 1);
+        }
+    }
+}
+
+#[test]
+fn stipulations_are_parsed() {
+    assert_eq!(Stipulation::parse("#2"), Some(Stipulation::Mate(2)));
+    assert_eq!(Stipulation::parse(" #12 "), Some(Stipulation::Mate(12)));
+    assert_eq!(Stipulation::parse("#0"), None);
+    assert_eq!(Stipulation::parse("2"), None);
+    assert_eq!(Stipulation::Mate(3).str(), "#3");
+    assert_eq!(Stipulation::parse("h#2"), Some(Stipulation::Helpmate(2)));
+    assert_eq!(Stipulation::parse("s#3"), Some(Stipulation::Selfmate(3)));
+    assert_eq!(Stipulation::parse("x#3"), None);
+    assert_eq!(Stipulation::Selfmate(3).str(), "s#3");
+}
+
+#[test]
+fn mate_in_one() {
+    // the side to move is already mated
+    let solution = Solver::new().solve(&BoardState::from_fen(board_state::CHECKMATE_TEST), Stipulation::Mate(1));
+    assert!(solution.keys.is_empty());
+    // the position the knight mated from
+    let bs = BoardState::from_fen("kr6/pp6/8/1N6/8/8/8/4K3 w - - 0 1");
+    let solution = Solver::new().solve(&bs, Stipulation::Mate(1));
+    assert!(!solution.is_cooked());
+    assert_eq!(solution.keys[0].chess_move.str(), "b5c7");
+    let mated = bs.make_move(solution.keys[0].chess_move).to_fen();
+    assert!(board_state::CHECKMATE_TEST.starts_with(mated.rsplitn(3, ' ').last().expect("a fen has clocks")));
+    assert_eq!(solution.str(&bs), "1. b5c7#!");
+}
+
+#[test]
+fn mate_in_two_with_its_tree() {
+    let bs = BoardState::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1");
+    let mut solver = Solver::new();
+    assert!(solver.solve(&bs, Stipulation::Mate(1)).keys.is_empty());
+    let solution = solver.solve(&bs, Stipulation::Mate(2));
+    assert_eq!(solution.keys.len(), 1);
+    assert_eq!(solution.keys[0].chess_move.str(), "d5f6");
+    assert_forces_mate(&bs, &solution.keys[0], 2);
+    assert_eq!(solution.str(&bs), "1. d5f6+!\n    1... g7f6\n        2. c4f7#");
+}
+
+#[test]
+fn cooks_are_found() {
+    let bs = BoardState::from_fen("7k/8/5K2/8/8/8/8/R7 w - - 0 1");
+    assert!(Solver::new().solve(&bs, Stipulation::Mate(1)).keys.is_empty());
+    let solution = Solver::new().solve(&bs, Stipulation::Mate(2));
+    assert!(solution.is_cooked());
+    let mut keys: Vec<String> = solution.keys.iter().map(|key| key.chess_move.str()).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["f6f7", "f6g6"]);
+    for key in &solution.keys {
+        assert_forces_mate(&bs, key, 2);
+    }
+}
+
+/// the helpmate lines as moves, each one ends in mate after exactly the plies of the stipulation
+fn help_lines(bs: &BoardState, nodes: &[SolutionNode], plies: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for node in nodes {
+        let new_state = bs.make_move(node.chess_move);
+        if plies == 1 {
+            assert!(new_state.is_checkmate());
+            lines.push(node.chess_move.str());
+            continue;
+        }
+        for line in help_lines(&new_state, &node.replies, plies - 1) {
+            lines.push(format!("{} {}", node.chess_move.str(), line));
+        }
+    }
+    return lines;
+}
+
+#[test]
+fn helpmates() {
+    let bs = BoardState::from_fen("7k/8/6K1/8/8/8/8/1Q6 b - - 0 1");
+    let solution = Solver::new().solve(&bs, Stipulation::Helpmate(1));
+    assert!(!solution.is_cooked());
+    assert_eq!(help_lines(&bs, &solution.keys, 2), vec!["h8g8 b1b8"]);
+    // with more moves there are many ways, none of them mates early
+    let solution = Solver::new().solve(&bs, Stipulation::Helpmate(2));
+    let lines = help_lines(&bs, &solution.keys, 4);
+    assert!(solution.is_cooked());
+    assert_eq!(lines.len(), solution.solution_count());
+    assert!(lines.contains(&String::from("h8g8 b1b7 g8h8 b7h7")));
+}
+
+#[test]
+fn selfmates() {
+    // the check leaves black only the bishop block, which uncovers the rook
+    let bs = BoardState::from_fen("8/8/5R2/8/4P3/4p1P1/8/1r1b1K1k w - - 0 1");
+    let solution = Solver::new().solve(&bs, Stipulation::Selfmate(1));
+    assert!(!solution.is_cooked());
+    assert_eq!(solution.str(&bs), "1. f6h6+!\n    1... d1h5#");
+    // shorter solutions count
+    assert!(Solver::new()
+        .solve(&bs, Stipulation::Selfmate(2))
+        .keys
+        .iter()
+        .any(|key| key.chess_move.str() == "f6h6"));
+    // mating black doesn't solve a selfmate
+    let bs = BoardState::from_fen("kr6/pp6/8/1N6/8/8/8/4K3 w - - 0 1");
+    assert!(Solver::new().solve(&bs, Stipulation::Selfmate(1)).keys.is_empty());
+}
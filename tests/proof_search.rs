@@ -0,0 +1,55 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::{self, BoardState};
+use chesstacean::proof_search::{prove, Goal, ProofResult, DEFAULT_MATERIAL_GAIN};
+
+fn line_string(fen: &str, goal: Goal, max_plies: u32) -> String {
+    let result = prove(&BoardState::from_fen(fen), goal, max_plies, 100_000);
+    assert_eq!(result.result, ProofResult::Proven, "{}", fen);
+    return result.line.iter().map(|chess_move| chess_move.str()).collect::<Vec<String>>().join(" ");
+}
+
+#[test]
+fn goals_are_parsed() {
+    assert_eq!(Goal::parse("mate"), Some(Goal::Mate));
+    assert_eq!(Goal::parse("material"), Some(Goal::Material(DEFAULT_MATERIAL_GAIN)));
+    assert_eq!(Goal::parse("material:500"), Some(Goal::Material(500)));
+    assert_eq!(Goal::parse("material:-5"), None);
+    assert_eq!(Goal::parse("draw"), None);
+}
+
+#[test]
+fn mates_are_proven() {
+    let fen = "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1";
+    assert_eq!(line_string(fen, Goal::Mate, 5), "d5f6 g7f6 c4f7");
+    let result = prove(&BoardState::from_fen(fen), Goal::Mate, 5, 100_000);
+    // the key, the only defense and the mate
+    assert_eq!(result.proof_tree_size, 4);
+    assert!(result.nodes >= result.proof_tree_size);
+    let bs = BoardState::from_fen("7k/8/5K2/8/8/8/8/R7 w - - 0 1");
+    assert_eq!(prove(&bs, Goal::Mate, 3, 100_000).result, ProofResult::Proven);
+    // the mate takes three plies
+    let result = prove(&bs, Goal::Mate, 2, 100_000);
+    assert_eq!(result.result, ProofResult::Disproven);
+    assert!(result.line.is_empty());
+    // the side to move is mated already
+    assert_eq!(
+        prove(&BoardState::from_fen(board_state::CHECKMATE_TEST), Goal::Mate, 3, 100_000).result,
+        ProofResult::Disproven
+    );
+}
+
+#[test]
+fn material_wins_are_proven() {
+    // the queen hangs
+    assert!(line_string("4k3/8/8/3q4/8/2N5/8/4K3 w - - 0 1", Goal::Material(DEFAULT_MATERIAL_GAIN), 4).starts_with("c3d5 "));
+    // the fork wins the rook after the king moves away
+    assert!(line_string("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1", Goal::Material(DEFAULT_MATERIAL_GAIN), 4).starts_with("b5c7 "));
+    let bs = BoardState::from_fen("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1");
+    assert_eq!(
+        prove(&bs, Goal::Material(DEFAULT_MATERIAL_GAIN), 4, 100_000).result,
+        ProofResult::Disproven
+    );
+    assert_eq!(prove(&bs, Goal::Material(DEFAULT_MATERIAL_GAIN), 20, 50).result, ProofResult::Unknown);
+}
@@ -0,0 +1,49 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::BoardState;
+use chesstacean::search::{SearchLimits, Searcher};
+
+const MIDDLEGAME: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+
+#[test]
+fn single_thread_search_is_deterministic() {
+    let bs = BoardState::from_fen(MIDDLEGAME);
+    let search = || {
+        let mut searcher = Searcher::new();
+        searcher.set_threads(1);
+        return searcher.search(&bs, &SearchLimits::depth(6));
+    };
+    let first = search();
+    let second = search();
+    assert!(first.best_move.is_some());
+    assert_eq!(first.best_move, second.best_move);
+    assert_eq!(first.score, second.score);
+    assert_eq!(first.nodes, second.nodes);
+    assert_eq!(first.pv, second.pv);
+}
+
+#[test]
+fn multi_pv_lines_start_with_different_moves_best_first() {
+    let bs = BoardState::from_fen(MIDDLEGAME);
+    let mut searcher = Searcher::new();
+    searcher.set_threads(1);
+    searcher.set_option("MultiPV", "4").expect("valid option");
+    let result = searcher.search(&bs, &SearchLimits::depth(4));
+    assert_eq!(result.lines.len(), 4);
+    let mut first_moves: Vec<String> = result.lines.iter().map(|line| line.pv[0].str()).collect();
+    first_moves.sort();
+    first_moves.dedup();
+    assert_eq!(first_moves.len(), 4);
+    assert!(result.lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    assert_eq!(result.best_move, Some(result.lines[0].pv[0]));
+    assert_eq!(result.score, result.lines[0].score);
+}
+
+#[test]
+fn hashfull_counts_the_entries_of_the_search() {
+    let mut searcher = Searcher::new();
+    searcher.set_option("Hash", "1").expect("valid option");
+    let result = searcher.search(&BoardState::from_fen(MIDDLEGAME), &SearchLimits::depth(6));
+    assert!((1..=1000).contains(&result.hashfull), "{}", result.hashfull);
+}
@@ -0,0 +1,136 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::path::Path;
+
+use chesstacean::board_state::BoardState;
+use chesstacean::search::{SearchLimits, Searcher};
+use chesstacean::syzygy::{Tablebases, Wdl};
+
+// the tests that probe need the published tables listed in tests/fixtures/syzygy/README.md, they fail without them,
+// run them with "cargo test --test syzygy -- --ignored" once the files are there or SYZYGY_PATH points at them
+
+/// tests/fixtures/syzygy, or the directories in SYZYGY_PATH
+fn tables_path() -> String {
+    return env::var("SYZYGY_PATH").unwrap_or_else(|_| {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("syzygy");
+        return fixtures.to_string_lossy().to_string();
+    });
+}
+
+fn tablebases() -> Tablebases {
+    return Tablebases::open(&tables_path()).expect("the directory has to contain Syzygy tables");
+}
+
+#[test]
+fn missing_directory_is_an_error() {
+    assert!(Tablebases::open("does/not/exist").is_err());
+}
+
+#[test]
+#[ignore = "needs the published Syzygy tables in tests/fixtures/syzygy"]
+fn wdl_of_simple_endings() {
+    let tablebases = tablebases();
+    let positions = [
+        ("8/8/8/4k3/8/8/8/KQ6 w - - 0 1", Wdl::Win),
+        ("8/8/8/4k3/8/8/8/KQ6 b - - 0 1", Wdl::Loss),
+        // the rook hangs
+        ("8/8/8/8/8/8/2k5/1R2K3 b - - 0 1", Wdl::Draw),
+        ("8/8/8/4k3/8/8/8/K7 w - - 0 1", Wdl::Draw),
+        // the king in front of the pawn on the sixth rank wins, the rook pawn doesn't
+        ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win),
+        ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss),
+        ("k7/8/1K6/P7/8/8/8/8 w - - 0 1", Wdl::Draw),
+    ];
+    for (fen, wdl) in positions {
+        assert_eq!(tablebases.probe_wdl(&BoardState::from_fen(fen)), Some(wdl), "{}", fen);
+    }
+}
+
+#[test]
+#[ignore = "needs the published Syzygy tables in tests/fixtures/syzygy"]
+fn dtz_and_root_moves() {
+    let tablebases = tablebases();
+    let bs = BoardState::from_fen("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1");
+    assert_eq!(tablebases.probe_dtz(&bs), Some(1));
+    let mate = bs.move_from_string("b1b8").expect("legal");
+    assert_eq!(tablebases.root_moves(&bs), Some(vec![mate]));
+    // black can only go to b8 and is mated by Rh8, the published tables round losses to full moves
+    let bs = BoardState::from_fen("k7/8/1K6/8/8/8/7R/8 b - - 0 1");
+    assert_eq!(tablebases.probe_wdl(&bs), Some(Wdl::Loss));
+    assert!(tablebases.probe_dtz(&bs).is_some_and(|dtz| (-3..=-2).contains(&dtz)));
+    let bs = bs.make_move(bs.move_from_string("a8b8").expect("legal"));
+    assert_eq!(tablebases.probe_dtz(&bs), Some(1));
+    assert_eq!(tablebases.root_moves(&bs), Some(vec![bs.move_from_string("h2h8").expect("legal")]));
+    let pawn = BoardState::from_fen("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1");
+    assert!(tablebases.probe_dtz(&pawn).is_some_and(|dtz| dtz < 0));
+    // positions with castling rights aren't in the tables
+    assert_eq!(tablebases.probe_wdl(&BoardState::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1")), None);
+}
+
+#[test]
+#[ignore = "needs the published Syzygy tables in tests/fixtures/syzygy"]
+fn search_only_plays_moves_that_keep_the_win() {
+    let path = tables_path();
+    let mut searcher = Searcher::new();
+    searcher.set_option("SyzygyPath", &path).expect("the tables can be read");
+    let bs = BoardState::from_fen("8/8/8/4k3/8/8/8/KQ6 w - - 0 1");
+    let best_move = searcher.search(&bs, &SearchLimits::depth(3)).best_move.expect("there are legal moves");
+    assert_eq!(
+        Tablebases::open(&path).expect("readable").probe_wdl(&bs.make_move(best_move)),
+        Some(Wdl::Loss)
+    );
+}
+
+#[test]
+#[ignore = "needs the published Syzygy tables in tests/fixtures/syzygy"]
+fn tables_are_probed_with_the_colors_swapped() {
+    let tablebases = tablebases();
+    // each position and the same one with the board mirrored and the colors swapped
+    let positions = [
+        ("8/8/8/4k3/8/8/8/KQ6 w - - 0 1", "kq6/8/8/8/4K3/8/8/8 b - - 0 1"),
+        ("8/8/8/4k3/8/8/8/KR6 b - - 0 1", "kr6/8/8/8/4K3/8/8/8 w - - 0 1"),
+        ("1r2k3/2K5/8/8/8/8/8/8 w - - 0 1", "8/8/8/8/8/8/2k5/1R2K3 b - - 0 1"),
+        ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", "8/8/8/8/4p3/4k3/8/4K3 b - - 0 1"),
+        ("8/8/8/8/8/3k4/1P6/2K5 b - - 0 1", "2k5/1p6/3K4/8/8/8/8/8 w - - 0 1"),
+        ("k7/8/1K6/P7/8/8/8/8 w - - 0 1", "8/8/8/8/p7/1k6/8/K7 b - - 0 1"),
+    ];
+    for (fen, flipped_fen) in positions {
+        let (bs, flipped) = (BoardState::from_fen(fen), BoardState::from_fen(flipped_fen));
+        assert!(tablebases.probe_wdl(&bs).is_some(), "{}", fen);
+        assert_eq!(tablebases.probe_wdl(&bs), tablebases.probe_wdl(&flipped), "{}", fen);
+        assert!(tablebases.probe_dtz(&bs).is_some(), "{}", fen);
+        assert_eq!(tablebases.probe_dtz(&bs), tablebases.probe_dtz(&flipped), "{}", fen);
+    }
+    // promoting right away resets the 50-move counter
+    for fen in ["8/3KP3/8/8/8/8/8/k7 w - - 0 1", "K7/8/8/8/8/8/3kp3/8 b - - 0 1"] {
+        assert_eq!(tablebases.probe_dtz(&BoardState::from_fen(fen)), Some(1), "{}", fen);
+    }
+    let rook = BoardState::from_fen("8/8/8/4k3/8/8/8/KR6 w - - 0 1");
+    assert!(tablebases.probe_dtz(&rook).is_some_and(|dtz| dtz > 1));
+}
+
+#[test]
+#[ignore = "needs the published Syzygy tables in tests/fixtures/syzygy"]
+fn four_piece_tables() {
+    let tablebases = tablebases();
+    assert!(tablebases.max_pieces() >= 4);
+    let positions = [
+        ("8/8/8/4k3/8/8/8/KBN5 w - - 0 1", Wdl::Win),
+        ("8/8/8/4k3/8/8/8/KBN5 b - - 0 1", Wdl::Loss),
+        // black's pieces are probed in the same table with the colors swapped
+        ("kbn5/8/8/8/4K3/8/8/8 b - - 0 1", Wdl::Win),
+        ("kbn5/8/8/8/4K3/8/8/8 w - - 0 1", Wdl::Loss),
+        // the knight hangs, bishop against king is a draw
+        ("8/8/8/8/8/3kN3/8/K6B b - - 0 1", Wdl::Draw),
+    ];
+    for (fen, wdl) in positions {
+        let bs = BoardState::from_fen(fen);
+        assert_eq!(tablebases.probe_wdl(&bs), Some(wdl), "{}", fen);
+        let dtz = tablebases.probe_dtz(&bs).expect("the DTZ table is there");
+        assert_eq!(dtz.signum(), wdl.value().signum(), "{}", fen);
+    }
+    let bs = BoardState::from_fen("8/8/8/8/8/3kN3/8/K6B b - - 0 1");
+    assert_eq!(tablebases.root_moves(&bs), Some(vec![bs.move_from_string("d3e3").expect("legal")]));
+}
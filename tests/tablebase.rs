@@ -0,0 +1,206 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::fs;
+use std::sync::mpsc;
+
+use chesstacean::board_state::BoardState;
+use chesstacean::search::{SearchLimits, Searcher};
+use chesstacean::tablebase::{self, Dtm, EndgameTables, Generator};
+
+/// the tables of the ending and everything it turns into, written to a fresh directory
+fn generate(name: &str, directory_name: &str) -> (Generator, String) {
+    let directory = env::temp_dir().join(directory_name);
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).expect("the temporary directory is writable");
+    let directory = directory.to_string_lossy().to_string();
+    let mut generator = Generator::new();
+    generator.generate(name).expect("the ending can be generated");
+    for table in generator.tables() {
+        table.save(&directory).expect("the table can be written");
+    }
+    return (generator, directory);
+}
+
+#[test]
+fn names_are_checked() {
+    let mut generator = Generator::new();
+    assert!(generator.generate("KQK").is_err());
+    assert!(generator.generate("KQvKRR").is_err());
+    assert!(generator.generate("KvK").is_err());
+    assert!(EndgameTables::load("does/not/exist").is_err());
+}
+
+#[test]
+fn progress_is_reported_per_table() {
+    let (sender, received) = mpsc::channel();
+    let mut generator = Generator::new();
+    generator.set_progress_callback(Box::new(move |table| {
+        sender
+            .send((table.name.clone(), table.positions, table.longest_mate))
+            .expect("the test waits for the tables");
+    }));
+    generator.generate("KRvK").expect("the ending can be generated");
+    let tables: Vec<(String, usize, u32)> = received.try_iter().collect();
+    assert_eq!(tables.len(), 1);
+    let (name, positions, longest_mate) = &tables[0];
+    assert_eq!((name.as_str(), *longest_mate), ("KRvK", 31));
+    assert!(*positions > 0);
+}
+
+#[test]
+fn queen_and_rook_mates() {
+    let (generator, directory) = generate("KRvK", "chesstacean_krvk");
+    let lengths: Vec<(&str, u32)> = generator.tables().iter().map(|table| (table.name(), table.longest_mate())).collect();
+    // the longest mates are known to be 10 and 16 moves
+    assert_eq!(lengths, vec![("KRvK", 31)]);
+    let (generator, directory_with_queen) = generate("KQvK", "chesstacean_kqvk");
+    assert_eq!(generator.tables()[0].longest_mate(), 19);
+    let endgame_tables = EndgameTables::load(&directory_with_queen).expect("the tables were written");
+    let bs = BoardState::from_fen("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1");
+    assert_eq!(endgame_tables.probe(&bs), Some(Dtm::Win(1)));
+    let (best_moves, dtm) = endgame_tables.best_moves(&bs).expect("the position is in the tables");
+    assert_eq!(dtm, Dtm::Win(1));
+    assert!(best_moves.contains(&bs.move_from_string("b1b8").expect("legal")));
+    assert!(best_moves.iter().all(|chess_move| bs.make_move(*chess_move).is_checkmate()));
+    assert!(matches!(
+        endgame_tables.probe(&BoardState::from_fen("7k/8/6K1/8/8/8/8/1Q6 b - - 0 1")),
+        Some(Dtm::Loss(_))
+    ));
+    // black's queen is looked up in the same table with the colors swapped
+    assert_eq!(
+        endgame_tables.probe(&BoardState::from_fen("1q6/8/8/8/8/6k1/8/7K b - - 0 1")),
+        Some(Dtm::Win(1))
+    );
+    assert_eq!(
+        endgame_tables.probe(&BoardState::from_fen("8/8/8/8/8/6k1/8/1q5K w - - 0 1")),
+        Some(Dtm::Loss(0))
+    );
+    // the rook table is in the other directory
+    assert_eq!(endgame_tables.probe(&BoardState::from_fen("8/8/8/4k3/8/8/8/KR6 w - - 0 1")), None);
+    let rook_tables = EndgameTables::load(&directory).expect("the tables were written");
+    assert!(matches!(
+        rook_tables.probe(&BoardState::from_fen("8/8/8/4k3/8/8/8/KR6 w - - 0 1")),
+        Some(Dtm::Win(_))
+    ));
+}
+
+#[test]
+fn pawn_endings() {
+    let (generator, directory) = generate("KPvK", "chesstacean_kpvk");
+    let names: Vec<&str> = generator.tables().iter().map(|table| table.name()).collect();
+    assert_eq!(names, vec!["KBvK", "KNvK", "KPvK", "KQvK", "KRvK"]);
+    let endgame_tables = EndgameTables::load(&directory).expect("the tables were written");
+    assert_eq!(endgame_tables.len(), 5);
+    // the king in front of the pawn on the sixth rank wins, the rook pawn doesn't
+    assert!(matches!(
+        endgame_tables.probe(&BoardState::from_fen("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")),
+        Some(Dtm::Win(_))
+    ));
+    assert!(matches!(
+        endgame_tables.probe(&BoardState::from_fen("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")),
+        Some(Dtm::Loss(_))
+    ));
+    assert_eq!(
+        endgame_tables.probe(&BoardState::from_fen("k7/8/1K6/P7/8/8/8/8 w - - 0 1")),
+        Some(Dtm::Draw)
+    );
+    // black pawns are looked up in the same table with the colors swapped
+    assert!(matches!(
+        endgame_tables.probe(&BoardState::from_fen("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1")),
+        Some(Dtm::Win(_))
+    ));
+    // positions with castling rights aren't in the tables
+    assert_eq!(endgame_tables.probe(&BoardState::from_fen("4k3/8/8/8/8/8/4P3/4K2R w K - 0 1")), None);
+
+    let mut searcher = Searcher::new();
+    searcher.set_option("TablebasePath", &directory).expect("the tables can be read");
+    let bs = BoardState::from_fen("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1");
+    let best_move = searcher.search(&bs, &SearchLimits::depth(3)).best_move.expect("there are legal moves");
+    let (best_moves, _) = endgame_tables.best_moves(&bs).expect("the position is in the tables");
+    assert!(best_moves.contains(&best_move));
+}
+
+/// the pieces and the side to move, the rest of the FEN doesn't matter to the tables
+fn placement_fen(bs: &BoardState) -> String {
+    return bs.to_fen().split(' ').take(2).collect::<Vec<&str>>().join(" ");
+}
+
+/// positions with the pieces on pseudo-random squares, the side that just moved isn't in check
+fn random_positions(pieces: &str, count: usize) -> Vec<BoardState> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut next_square = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        return (state % 64) as usize;
+    };
+    let mut positions = Vec::new();
+    while positions.len() < count {
+        let mut board = ['.'; 64];
+        let mut valid = true;
+        for letter in pieces.chars() {
+            let square = next_square();
+            let is_pawn = letter.eq_ignore_ascii_case(&'p');
+            valid &= board[square] == '.' && (!is_pawn || (8..56).contains(&square));
+            board[square] = letter;
+        }
+        if !valid {
+            continue;
+        }
+        let ranks: Vec<String> = (0..8).rev().map(|rank| board[rank * 8..rank * 8 + 8].iter().collect()).collect();
+        let side = if positions.len() % 2 == 0 { "w" } else { "b" };
+        let fen = format!("{} {} - - 0 1", ranks.join("/"), side);
+        let fen = ["8", "7", "6", "5", "4", "3", "2"]
+            .iter()
+            .fold(fen, |fen, empty| fen.replace(&".".repeat(empty.parse().unwrap()), empty));
+        let fen = fen.replace('.', "1");
+        let bs = BoardState::from_fen(&fen);
+        if !bs.make_null_move().is_in_check() {
+            positions.push(bs);
+        }
+    }
+    return positions;
+}
+
+#[test]
+fn generator_moves_match_the_legal_moves() {
+    for pieces in ["KQk", "KRk", "KBk", "KNk", "KPk", "KPkp", "KRkb", "KQkn", "KPPk", "KBNk", "KRkp", "KQkq"] {
+        for bs in random_positions(pieces, 300) {
+            let fen = bs.to_fen();
+            let mut expected: Vec<String> = bs
+                .get_legal_moves()
+                .iter()
+                .map(|chess_move| placement_fen(&bs.make_move(*chess_move)))
+                .collect();
+            let mut successors: Vec<String> = tablebase::successors(&bs).expect("few enough pieces").iter().map(placement_fen).collect();
+            expected.sort();
+            successors.sort();
+            assert_eq!(successors, expected, "{}", fen);
+            // every predecessor has a move to the position and every quiet move can be taken back
+            let position = placement_fen(&bs);
+            for predecessor in tablebase::predecessors(&bs).expect("few enough pieces") {
+                let reaches_position = predecessor
+                    .get_legal_moves()
+                    .iter()
+                    .any(|chess_move| placement_fen(&predecessor.make_move(*chess_move)) == position);
+                assert!(reaches_position, "{} from {}", fen, predecessor.to_fen());
+            }
+            for chess_move in bs.get_legal_moves() {
+                let is_promotion = chess_move.str().len() == 5;
+                if bs.is_capture(chess_move) || is_promotion {
+                    continue;
+                }
+                let new_state = bs.make_move(chess_move);
+                let predecessors = tablebase::predecessors(&new_state).expect("few enough pieces");
+                assert!(
+                    predecessors.iter().any(|predecessor| placement_fen(predecessor) == position),
+                    "{} {}",
+                    fen,
+                    chess_move.str()
+                );
+            }
+        }
+    }
+}
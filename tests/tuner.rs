@@ -0,0 +1,32 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::fs;
+
+use chesstacean::tuner;
+
+// the results don't match the material, so the weights have something to learn
+const POSITIONS: &str = "\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 [0.5]
+rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 1-0
+4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 1/2-1/2
+4k3/8/8/3q4/8/8/8/R3K3 w - - 0 1 1-0
+r3k3/8/8/8/3Q4/8/8/4K3 b - - 0 1 0-1
+4k3/pppp4/8/8/8/8/5PPP/4K3 w - - 0 1 1-0
+";
+
+#[test]
+fn tuning_lowers_the_error() {
+    let directory = env::temp_dir().join(format!("chesstacean_tuner_{}", std::process::id()));
+    fs::create_dir_all(&directory).expect("temporary directory");
+    let positions_path = directory.join("positions.txt");
+    let output_path = directory.join("eval_parameters.rs");
+    fs::write(&positions_path, POSITIONS).expect("positions written");
+    let (start_error, end_error) =
+        tuner::tune(positions_path.to_str().expect("valid path"), output_path.to_str().expect("valid path"), 1).expect("tuning works");
+    assert!(end_error < start_error, "{} not below {}", end_error, start_error);
+    let source = fs::read_to_string(&output_path).expect("weights written");
+    assert!(source.contains("pub const DEFAULT_PARAMETERS: EvalParameters"));
+    fs::remove_dir_all(&directory).expect("temporary directory removed");
+}
@@ -0,0 +1,61 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::time::Duration;
+
+use chesstacean::board_state::{BLACK, WHITE};
+use chesstacean::engine::time_for_move;
+use chesstacean::uci::parse_go;
+
+fn tokens(command: &str) -> Vec<&str> {
+    return command.split_whitespace().collect();
+}
+
+#[test]
+fn go_limits_are_parsed() {
+    let (limits, infinite) = parse_go(&tokens("depth 7 nodes 5000"), WHITE);
+    assert_eq!(limits.depth, 7);
+    assert_eq!(limits.nodes, Some(5000));
+    assert_eq!(limits.time, None);
+    assert!(!infinite && !limits.ponder);
+    let (limits, infinite) = parse_go(&tokens("infinite"), WHITE);
+    assert!(infinite);
+    assert_eq!(limits.time, None);
+    let (limits, _) = parse_go(&tokens("movetime 2500"), BLACK);
+    assert_eq!(limits.time, Some(Duration::from_millis(2500)));
+}
+
+#[test]
+fn the_clock_of_the_side_to_move_is_used() {
+    let command = tokens("ponder wtime 60000 btime 3000 winc 1000 binc 0 movestogo 10");
+    let (limits, _) = parse_go(&command, WHITE);
+    assert!(limits.ponder);
+    assert_eq!(
+        limits.time,
+        Some(time_for_move(Duration::from_millis(60000), Duration::from_millis(1000), Some(10)))
+    );
+    let (limits, _) = parse_go(&command, BLACK);
+    assert_eq!(limits.time, Some(time_for_move(Duration::from_millis(3000), Duration::ZERO, Some(10))));
+}
+
+#[test]
+fn an_overstepped_clock_still_limits_the_search() {
+    let (limits, _) = parse_go(&tokens("wtime -50 btime 1000 winc 0 binc 0"), WHITE);
+    assert_eq!(limits.time, Some(Duration::from_millis(1)));
+}
+
+#[test]
+fn time_for_move_splits_the_clock() {
+    // a thirtieth of the time left and most of the increment
+    assert_eq!(time_for_move(Duration::from_secs(60), Duration::ZERO, None), Duration::from_secs(2));
+    assert_eq!(
+        time_for_move(Duration::from_secs(60), Duration::from_secs(4), Some(20)),
+        Duration::from_secs(6)
+    );
+    // never more than the clock has left after the overhead, never nothing at all
+    assert_eq!(
+        time_for_move(Duration::from_millis(100), Duration::from_secs(10), Some(1)),
+        Duration::from_millis(70)
+    );
+    assert_eq!(time_for_move(Duration::ZERO, Duration::ZERO, Some(0)), Duration::from_millis(1));
+}
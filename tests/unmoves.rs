@@ -0,0 +1,115 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::{self, BoardState, Move, Position};
+
+/// the board, the side to move, the castling rights and the en passant square, the clocks of predecessors are a guess
+fn without_clocks(bs: &BoardState) -> String {
+    return bs.to_fen().split(' ').take(4).collect::<Vec<&str>>().join(" ");
+}
+
+fn is_predecessor(bs: &BoardState, previous: &BoardState, chess_move: Move) -> bool {
+    return bs
+        .make_move(chess_move)
+        .unmoves()
+        .iter()
+        .any(|(position, unmove)| *unmove == chess_move && without_clocks(position) == without_clocks(previous));
+}
+
+#[test]
+fn every_move_can_be_taken_back() {
+    let fens = [
+        board_state::STARTING_FEN,
+        board_state::E4_FEN,
+        board_state::CHECKMATE_TEST,
+        "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ];
+    for fen in fens {
+        let mut bs = BoardState::from_fen(fen);
+        // a few plies into the game as well, always taking the move in the middle of the list
+        for _ in 0..6 {
+            let moves = bs.get_legal_moves();
+            for chess_move in &moves {
+                assert!(
+                    is_predecessor(&bs, &bs, *chess_move),
+                    "{} can't be taken back in {}",
+                    chess_move.str(),
+                    bs.to_fen()
+                );
+            }
+            let Some(chess_move) = moves.get(moves.len() / 2) else {
+                break;
+            };
+            bs = bs.make_move(*chess_move);
+        }
+    }
+}
+
+#[test]
+fn predecessors_lead_to_the_position() {
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        "1Q2k3/8/8/8/8/8/8/4K3 b - - 0 1",
+        "8/8/8/2k5/8/3p4/8/4K3 w - - 0 1",
+    ] {
+        let bs = BoardState::from_fen(fen);
+        let unmoves = bs.unmoves();
+        assert!(!unmoves.is_empty());
+        for (previous, chess_move) in unmoves {
+            assert!(previous.is_legal_move(chess_move), "{} in {}", chess_move.str(), previous.to_fen());
+            assert_eq!(without_clocks(&previous.make_move(chess_move)), without_clocks(&bs));
+        }
+    }
+}
+
+#[test]
+fn captures_promotions_and_en_passant_are_taken_back() {
+    // the queen may have been a pawn on b7 or on a7 or c7 that took something
+    let bs = BoardState::from_fen("1Q2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    let promotions: Vec<String> = bs
+        .unmoves()
+        .iter()
+        .filter(|(previous, _)| previous.to_fen().starts_with("4k3/1P6"))
+        .map(|(_, chess_move)| chess_move.str())
+        .collect();
+    assert_eq!(promotions, vec!["b7b8q"]);
+    let has = |fen: &str| bs.unmoves().iter().any(|(previous, _)| without_clocks(previous) == fen);
+    assert!(has("1r2k3/P7/8/8/8/8/8/4K3 w - -"));
+    assert!(has("1n2k3/2P5/8/8/8/8/8/4K3 w - -"));
+    // the pawn on d3 took a pawn that had just moved from d2 to d4
+    let bs = BoardState::from_fen("8/8/8/2k5/8/3p4/8/4K3 w - - 0 1");
+    assert!(bs
+        .unmoves()
+        .iter()
+        .any(|(previous, chess_move)| chess_move.str() == "e4d3" && without_clocks(previous) == "8/8/8/2k5/3Pp3/8/8/4K3 b - d3"));
+}
+
+#[test]
+fn predecessors_have_possible_material() {
+    // black has all of its pieces, so the knight didn't capture anything
+    let bs = BoardState::from_fen("rnbqkbnr/pppppppp/8/3N4/8/8/8/4K3 b kq - 0 1");
+    let unmoves = bs.unmoves();
+    assert!(!unmoves.is_empty());
+    assert!(unmoves.iter().all(|(previous, chess_move)| !previous.is_capture(*chess_move)));
+    // with eight pawns the queen can't have been a ninth one
+    let bs = BoardState::from_fen("1Q2k3/8/8/8/8/8/PPPPPPPP/4K3 b - - 0 1");
+    assert!(!bs.unmoves().is_empty());
+    assert!(bs.unmoves().iter().all(|(_, chess_move)| chess_move.str().len() == 4));
+    // black misses a knight and the bishop of the dark squares, a bishop taken on the light square d5 would have
+    // been a second one on light squares with all eight pawns still there
+    let bs = BoardState::from_fen("r1bqk1nr/pppppppp/8/3N4/8/8/8/4K3 b kq - 0 1");
+    let d5 = Position::position_from_string("d5");
+    let mut captured: Vec<i32> = bs
+        .unmoves()
+        .iter()
+        .filter(|(_, chess_move)| chess_move.to() == d5)
+        .map(|(previous, _)| previous.get_piece_at_position(d5))
+        .collect();
+    captured.sort();
+    captured.dedup();
+    assert_eq!(captured, vec![board_state::NOTHING, board_state::KNIGHT | board_state::BLACK]);
+}
@@ -0,0 +1,103 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use chesstacean::board_state::STARTING_FEN;
+use chesstacean::engine::time_for_move;
+use chesstacean::xboard::XBoard;
+
+/// a fresh game in force mode, so no command starts a search
+fn forced_game(commands: &[&str]) -> XBoard {
+    let (events, _) = mpsc::channel();
+    let mut xboard = XBoard::new(events);
+    for command in ["new", "force"].iter().chain(commands) {
+        assert!(xboard.handle_command(command));
+    }
+    return xboard;
+}
+
+#[test]
+fn level_sets_the_time_control() {
+    let xboard = forced_game(&["level 40 5 0"]);
+    assert_eq!(
+        xboard.search_limits().time,
+        Some(time_for_move(Duration::from_secs(300), Duration::ZERO, Some(40)))
+    );
+    // minutes:seconds with an increment and no session length, the clock is updated by "time"
+    let xboard = forced_game(&["level 0 2:30 5", "time 10000", "otim 10000"]);
+    assert_eq!(
+        xboard.search_limits().time,
+        Some(time_for_move(Duration::from_secs(100), Duration::from_secs(5), None))
+    );
+}
+
+#[test]
+fn falling_behind_the_opponent_shortens_the_moves() {
+    let usual = time_for_move(Duration::from_secs(50), Duration::from_secs(5), None);
+    let xboard = forced_game(&["level 0 2:30 5", "time 5000", "otim 10000"]);
+    assert_eq!(xboard.search_limits().time, Some(usual / 2));
+    let xboard = forced_game(&["level 0 2:30 5", "time 5000", "otim 6000"]);
+    let time = xboard.search_limits().time.unwrap();
+    assert!(time > usual / 2 && time < usual);
+    // being ahead doesn't change anything
+    let xboard = forced_game(&["level 0 2:30 5", "time 5000", "otim 1000"]);
+    assert_eq!(xboard.search_limits().time, Some(usual));
+}
+
+#[test]
+fn fixed_time_and_depth() {
+    let xboard = forced_game(&["level 40 5 0", "st 5", "sd 4"]);
+    let limits = xboard.search_limits();
+    assert_eq!(limits.time, Some(Duration::from_secs(5)));
+    assert_eq!(limits.depth, 4);
+    // a new time control replaces the fixed time
+    let xboard = forced_game(&["st 5", "level 40 5 0"]);
+    assert_eq!(
+        xboard.search_limits().time,
+        Some(time_for_move(Duration::from_secs(300), Duration::ZERO, Some(40)))
+    );
+    assert_eq!(forced_game(&["sd 0"]).search_limits().depth, 1);
+}
+
+#[test]
+fn user_moves_are_played() {
+    let mut xboard = forced_game(&["usermove e2e4", "usermove e7e5"]);
+    assert_eq!(
+        xboard.position().to_fen(),
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+    );
+    // illegal moves are rejected and leave the position alone
+    let fen = xboard.position().to_fen();
+    assert!(xboard.handle_command("usermove e4e6"));
+    assert!(xboard.handle_command("usermove e9e4"));
+    assert_eq!(xboard.position().to_fen(), fen);
+    assert!(xboard.handle_command("setboard 8/P6k/8/8/8/8/8/K7 w - -"));
+    assert!(xboard.handle_command("usermove a7a8n"));
+    assert_eq!(xboard.position().to_fen(), "N7/7k/8/8/8/8/8/K7 b - - 0 1");
+    assert!(xboard.handle_command("new"));
+    assert_eq!(xboard.position().to_fen(), STARTING_FEN);
+}
+
+#[test]
+fn malformed_positions_are_rejected() {
+    let mut xboard = forced_game(&["setboard 8/P6k/8/8/8/8/8/K7 w - -"]);
+    let fen = xboard.position().to_fen();
+    for position in [
+        "8/P6k/8/8/8/8/K7 w - -",
+        "8/P6k/8/8/8/8/8/K8 w - -",
+        "8/P6k/8/8/8/8/8/X7 w - -",
+        "8/P7/8/8/8/8/8/K7 w - -",
+        "P7/7k/8/8/8/8/8/K7 w - -",
+        "8/P6k/8/8/8/8/8/K7 x - -",
+        "8/P6k/8/8/8/8/8/K7 w X -",
+        "8/P6k/8/8/8/8/8/K7 w - z9",
+        "8/P6k/8/8/8/8/8/K7 w - - a b",
+        // white to move with black in check
+        "7k/8/8/8/8/8/8/K6R w - -",
+    ] {
+        assert!(xboard.handle_command(&format!("setboard {}", position)));
+        assert_eq!(xboard.position().to_fen(), fen, "{}", position);
+    }
+}
@@ -0,0 +1,47 @@
+// explicit returns are the style of this project
+#![allow(clippy::needless_return)]
+
+use chesstacean::board_state::{self, BoardState};
+
+/// the key a position gets when it is set up from scratch
+fn fresh_key(bs: &BoardState) -> u64 {
+    return BoardState::from_fen(&bs.to_fen()).zobrist_key();
+}
+
+#[test]
+fn incremental_keys_match_fresh_keys() {
+    let fens = [
+        board_state::STARTING_FEN,
+        // castling on both sides and captures of the rooks in their corners
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        // en passant right away and promotions soon after
+        "8/2p5/3p4/KP5r/1R3pPk/8/4P3/8 b - g3 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    ];
+    let mut seed: u64 = 7;
+    for fen in fens {
+        for _ in 0..20 {
+            let mut bs = BoardState::from_fen(fen);
+            for _ in 0..40 {
+                assert_eq!(bs.zobrist_key(), fresh_key(&bs), "{}", bs.to_fen());
+                let moves = bs.get_legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                bs = bs.make_move(moves[(seed >> 33) as usize % moves.len()]);
+            }
+        }
+    }
+    let bs = BoardState::from_fen("rnbqkbnr/pppp1ppp/8/8/3pP3/8/PPP2PPP/RNBQKBNR b KQkq e3 0 1");
+    assert_eq!(bs.make_null_move().zobrist_key(), fresh_key(&bs.make_null_move()));
+    assert_eq!(bs.mirrored().zobrist_key(), fresh_key(&bs.mirrored()));
+}
+
+#[test]
+fn predecessors_have_fresh_keys() {
+    let bs = BoardState::from_fen("r3k2r/8/8/3pP3/8/8/8/R4RK1 w kq d6 0 2");
+    for (previous, _) in bs.unmoves() {
+        assert_eq!(previous.zobrist_key(), fresh_key(&previous), "{}", previous.to_fen());
+    }
+}